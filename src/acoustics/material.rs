@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+use crate::acoustics::propagation::PropagationPaths;
+use crate::audio::engine::BandGains;
+use crate::extras::components::Ground;
+
+pub const USER_MATERIALS_FILE: &str = "materials.toml";
+
+/// Octave-band surface properties, one value per entry of `BAND_CENTERS`.
+#[derive(Component, Clone, Debug, Deserialize)]
+pub struct AcousticMaterial {
+    pub name: String,
+    pub absorption: [f32; 8],
+    pub scattering: [f32; 8],
+}
+
+impl AcousticMaterial {
+    /// Amplitude left after one bounce, per band.
+    pub fn reflection(&self) -> BandGains {
+        BandGains(self.absorption.map(|a| (1.0 - a.clamp(0.0, 1.0)).sqrt()))
+    }
+
+    /// Share of energy scattered diffusely around 500 Hz - 1 kHz.
+    pub fn mid_scattering(&self) -> f32 {
+        (self.scattering[3] + self.scattering[4]) / 2.0
+    }
+}
+
+impl Default for AcousticMaterial {
+    fn default() -> Self {
+        BUILT_IN_MATERIALS[0].to_material()
+    }
+}
+
+struct BuiltIn {
+    name: &'static str,
+    absorption: [f32; 8],
+    scattering: [f32; 8],
+}
+
+impl BuiltIn {
+    fn to_material(&self) -> AcousticMaterial {
+        AcousticMaterial {
+            name: self.name.to_string(),
+            absorption: self.absorption,
+            scattering: self.scattering,
+        }
+    }
+}
+
+//                              63    125   250   500   1k    2k    4k    8k
+const BUILT_IN_MATERIALS: [BuiltIn; 9] = [
+    BuiltIn {
+        name: "concrete",
+        absorption: [0.01, 0.01, 0.01, 0.02, 0.02, 0.02, 0.02, 0.03],
+        scattering: [0.05, 0.05, 0.05, 0.05, 0.05, 0.05, 0.05, 0.05],
+    },
+    BuiltIn {
+        name: "brick",
+        absorption: [0.03, 0.03, 0.03, 0.03, 0.04, 0.05, 0.07, 0.07],
+        scattering: [0.05, 0.05, 0.10, 0.15, 0.20, 0.25, 0.30, 0.30],
+    },
+    BuiltIn {
+        name: "plaster",
+        absorption: [0.01, 0.01, 0.02, 0.02, 0.03, 0.04, 0.05, 0.05],
+        scattering: [0.05, 0.05, 0.05, 0.05, 0.10, 0.10, 0.10, 0.10],
+    },
+    BuiltIn {
+        name: "wood",
+        absorption: [0.30, 0.28, 0.22, 0.17, 0.09, 0.10, 0.11, 0.11],
+        scattering: [0.05, 0.05, 0.10, 0.10, 0.10, 0.15, 0.15, 0.15],
+    },
+    BuiltIn {
+        name: "glass",
+        absorption: [0.20, 0.18, 0.06, 0.04, 0.03, 0.02, 0.02, 0.02],
+        scattering: [0.05, 0.05, 0.05, 0.05, 0.05, 0.05, 0.05, 0.05],
+    },
+    BuiltIn {
+        name: "carpet",
+        absorption: [0.02, 0.02, 0.06, 0.14, 0.37, 0.60, 0.65, 0.65],
+        scattering: [0.05, 0.05, 0.10, 0.10, 0.20, 0.20, 0.25, 0.25],
+    },
+    BuiltIn {
+        name: "curtains",
+        absorption: [0.10, 0.14, 0.35, 0.55, 0.72, 0.70, 0.65, 0.65],
+        scattering: [0.05, 0.10, 0.20, 0.30, 0.40, 0.50, 0.60, 0.60],
+    },
+    BuiltIn {
+        name: "acoustic tile",
+        absorption: [0.40, 0.50, 0.70, 0.60, 0.70, 0.70, 0.50, 0.50],
+        scattering: [0.10, 0.10, 0.15, 0.20, 0.25, 0.30, 0.35, 0.35],
+    },
+    BuiltIn {
+        name: "grass",
+        absorption: [0.10, 0.11, 0.26, 0.60, 0.69, 0.92, 0.99, 0.99],
+        scattering: [0.20, 0.20, 0.30, 0.40, 0.50, 0.60, 0.70, 0.70],
+    },
+];
+
+#[derive(Deserialize)]
+struct MaterialFile {
+    material: Vec<AcousticMaterial>,
+}
+
+/// Built-in materials followed by any from `USER_MATERIALS_FILE`.
+#[derive(Resource)]
+pub struct MaterialLibrary {
+    pub materials: Vec<AcousticMaterial>,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> Self {
+        Self {
+            materials: BUILT_IN_MATERIALS.iter().map(BuiltIn::to_material).collect(),
+        }
+    }
+}
+
+impl MaterialLibrary {
+    pub fn get(&self, name: &str) -> Option<&AcousticMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+
+    /// Adds the materials in a TOML file of `[[material]]` tables, replacing any with the same name.
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: MaterialFile = toml::from_str(&text).map_err(|err| err.to_string())?;
+        let count = file.material.len();
+        for material in file.material {
+            self.materials.retain(|existing| existing.name != material.name);
+            self.materials.push(material);
+        }
+        Ok(count)
+    }
+}
+
+/// The built-in materials with `USER_MATERIALS_FILE` on top, read before anything that
+/// looks materials up by name is built.
+pub fn load_user_materials() -> MaterialLibrary {
+    let mut library = MaterialLibrary::default();
+    let path = Path::new(USER_MATERIALS_FILE);
+    if !path.exists() { return library }
+    match library.load(path) {
+        Ok(count) => info!("Loaded {count} materials from {}", path.display()),
+        Err(err) => warn!("Could not load {}: {err}", path.display()),
+    }
+    library
+}
+
+pub fn cycle_ground_material(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    library: Res<MaterialLibrary>,
+    mut ground_query: Query<&mut AcousticMaterial, With<Ground>>,
+) {
+    if !keyboard.just_pressed(KeyCode::KeyG) { return }
+    for mut material in ground_query.iter_mut() {
+        let current = library.materials.iter().position(|m| m.name == material.name);
+        let next = current.map_or(0, |index| (index + 1) % library.materials.len());
+        *material = library.materials[next].clone();
+    }
+}
+
+/// Reports how the ground changes each emitter's first-order floor bounce.
+pub fn report_ground_reflection(
+    ground_query: Query<(Ref<AcousticMaterial>, &GlobalTransform), With<Ground>>,
+    paths: Res<PropagationPaths>,
+) {
+    let Ok((material, transform)) = ground_query.get_single() else { return };
+    if !material.is_changed() { return }
+    let height = transform.translation().y;
+    for (entity, emitter_paths) in &paths.emitters {
+        let Some(bounce) = emitter_paths
+            .iter()
+            .find(|path| path.order == 1 && (path.points[1].y - height).abs() < 1e-3)
+        else {
+            continue;
+        };
+        info!(
+            "{}: {entity:?} floor bounce {:.3} at 125 Hz, {:.3} at 1 kHz, {:.3} at 4 kHz",
+            material.name,
+            bounce.gain() * bounce.bands.at(125.0),
+            bounce.gain() * bounce.bands.at(1000.0),
+            bounce.gain() * bounce.bands.at(4000.0),
+        );
+    }
+}
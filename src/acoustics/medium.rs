@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use crate::audio::engine::{BandGains, BAND_CENTERS};
+
+/// The air every path travels through.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct Medium {
+    pub temperature: f32, // in °C
+    pub humidity: f32,    // relative, in %
+    pub pressure: f32,    // in kPa
+}
+
+impl Default for Medium {
+    fn default() -> Self {
+        Self {
+            temperature: 20.0,
+            humidity: 50.0,
+            pressure: 101.325,
+        }
+    }
+}
+
+const REFERENCE_PRESSURE: f32 = 101.325; // kPa
+const REFERENCE_TEMPERATURE: f32 = 293.15; // K
+const TRIPLE_POINT: f32 = 273.16; // K
+
+impl Medium {
+    fn kelvin(&self) -> f32 {
+        self.temperature + 273.15
+    }
+
+    /// In m/s.
+    pub fn speed_of_sound(&self) -> f32 {
+        331.3 * (self.kelvin() / 273.15).sqrt()
+    }
+
+    /// Pure-tone atmospheric attenuation in dB per metre, after ISO 9613-1.
+    pub fn absorption(&self, frequency: f32) -> f32 {
+        let t = self.kelvin() / REFERENCE_TEMPERATURE;
+        let p = self.pressure / REFERENCE_PRESSURE;
+        let saturation = 10f32.powf(-6.8346 * (TRIPLE_POINT / self.kelvin()).powf(1.261) + 4.6151);
+        // Molar concentration of water vapour, in %
+        let h = self.humidity * saturation / p;
+        let oxygen = p * (24.0 + 4.04e4 * h * (0.02 + h) / (0.391 + h));
+        let nitrogen = p * t.powf(-0.5) * (9.0 + 280.0 * h * (-4.170 * (t.powf(-1.0 / 3.0) - 1.0)).exp());
+        let f2 = frequency * frequency;
+        8.686 * f2 * (1.84e-11 / p * t.sqrt()
+            + t.powf(-2.5)
+                * (0.01275 * (-2239.1 / self.kelvin()).exp() / (oxygen + f2 / oxygen)
+                    + 0.1068 * (-3352.0 / self.kelvin()).exp() / (nitrogen + f2 / nitrogen)))
+    }
+
+    /// Amplitude left per octave band after travelling `distance` metres.
+    pub fn attenuation(&self, distance: f32) -> BandGains {
+        BandGains(BAND_CENTERS.map(|frequency| 10f32.powf(-self.absorption(frequency) * distance / 20.0)))
+    }
+}
+
+pub fn medium_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut medium: ResMut<Medium>,
+) {
+    let mut changed = false;
+    for (key, humidity, temperature) in [
+        (KeyCode::Minus, -5.0, 0.0),
+        (KeyCode::Equal, 5.0, 0.0),
+        (KeyCode::Digit9, 0.0, -1.0),
+        (KeyCode::Digit0, 0.0, 1.0),
+    ] {
+        if keyboard.just_pressed(key) {
+            medium.humidity = (medium.humidity + humidity).clamp(0.0, 100.0);
+            medium.temperature = (medium.temperature + temperature).clamp(-20.0, 50.0);
+            changed = true;
+        }
+    }
+    if changed {
+        info!(
+            "Air at {:.0} °C, {:.0}% RH: c = {:.1} m/s, {:.3} dB/m at 4 kHz, {:.3} dB/m at 8 kHz",
+            medium.temperature,
+            medium.humidity,
+            medium.speed_of_sound(),
+            medium.absorption(4000.0),
+            medium.absorption(8000.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ISO 9613-2 table 2, in dB/km at 101.325 kPa for the octave bands 63 Hz to 8 kHz
+    const TABLE: [(f32, f32, [f32; 8]); 6] = [
+        (10.0, 70.0, [0.1, 0.4, 1.0, 1.9, 3.7, 9.7, 32.8, 117.0]),
+        (20.0, 70.0, [0.1, 0.3, 1.1, 2.8, 5.0, 9.0, 22.9, 76.6]),
+        (30.0, 70.0, [0.1, 0.3, 1.0, 3.1, 7.4, 12.7, 23.1, 59.3]),
+        (15.0, 20.0, [0.3, 0.6, 1.2, 2.7, 8.2, 28.2, 88.8, 202.0]),
+        (15.0, 50.0, [0.1, 0.5, 1.2, 2.2, 4.2, 10.8, 36.2, 129.0]),
+        (15.0, 80.0, [0.1, 0.3, 1.1, 2.4, 4.1, 8.3, 23.7, 82.8]),
+    ];
+
+    #[test]
+    fn absorption_matches_the_standard() {
+        for (temperature, humidity, expected) in TABLE {
+            let medium = Medium { temperature, humidity, ..default() };
+            for (frequency, expected) in BAND_CENTERS.into_iter().zip(expected) {
+                let absorption = medium.absorption(frequency) * 1000.0;
+                // The table is rounded to a tenth, and a little coarser at the top
+                let tolerance = 0.06f32.max(expected * 0.015);
+                assert!(
+                    (absorption - expected).abs() <= tolerance,
+                    "{frequency} Hz at {temperature} °C, {humidity}% RH: {absorption} dB/km, not {expected}",
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn attenuation_compounds_with_distance() {
+        let medium = Medium::default();
+        let near = medium.attenuation(100.0).0;
+        let far = medium.attenuation(200.0).0;
+        for (near, far) in near.iter().zip(far) {
+            assert!((near * near - far).abs() < 1e-5);
+        }
+        assert!(near.windows(2).all(|pair| pair[0] > pair[1]), "higher bands should lose more");
+        assert!((Medium { temperature: 0.0, ..default() }.speed_of_sound() - 331.3).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::f32::consts::{PI, TAU};
+use crate::acoustics::medium::Medium;
+use crate::acoustics::room::Room;
+use crate::extras::resources::SimulationTime;
+
+const SLICE_RESOLUTION: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModeKind {
+    Axial,
+    Tangential,
+    Oblique,
+}
+
+/// A standing wave of a rigid shoebox, `indices` counting half wavelengths along x, y and z.
+#[derive(Clone, Copy, Debug)]
+pub struct RoomMode {
+    pub indices: UVec3,
+    pub frequency: f32, // in Hz
+    pub kind: ModeKind,
+}
+
+impl RoomMode {
+    /// Relative pressure, -1 to 1, at `local` measured from the room's minimum corner.
+    pub fn pressure(&self, size: Vec3, local: Vec3) -> f32 {
+        let phase = self.indices.as_vec3() * PI * local / size;
+        phase.x.cos() * phase.y.cos() * phase.z.cos()
+    }
+}
+
+/// Every mode of a `size` shoebox up to `max_frequency`, lowest first.
+pub fn room_modes(size: Vec3, speed_of_sound: f32, max_frequency: f32) -> Vec<RoomMode> {
+    let limit = (2.0 * max_frequency * size / speed_of_sound).floor().as_uvec3();
+    let mut modes = Vec::new();
+    for nx in 0..=limit.x {
+        for ny in 0..=limit.y {
+            for nz in 0..=limit.z {
+                let indices = UVec3::new(nx, ny, nz);
+                let nonzero = [nx, ny, nz].iter().filter(|n| **n > 0).count();
+                let kind = match nonzero {
+                    0 => continue,
+                    1 => ModeKind::Axial,
+                    2 => ModeKind::Tangential,
+                    _ => ModeKind::Oblique,
+                };
+                let frequency = speed_of_sound / 2.0 * (indices.as_vec3() / size).length();
+                if frequency <= max_frequency {
+                    modes.push(RoomMode { indices, frequency, kind });
+                }
+            }
+        }
+    }
+    modes.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    modes
+}
+
+#[derive(Resource)]
+pub struct ModeView {
+    pub modes: Vec<RoomMode>,
+    pub selected: usize,
+    pub max_frequency: f32,
+    pub visible: bool,
+    pub slice_height: f32, // above the floor, in m
+}
+
+impl Default for ModeView {
+    fn default() -> Self {
+        Self {
+            modes: Vec::new(),
+            selected: 0,
+            max_frequency: 60.0,
+            visible: false,
+            slice_height: 1.2,
+        }
+    }
+}
+
+impl ModeView {
+    pub fn current(&self) -> Option<&RoomMode> {
+        self.modes.get(self.selected)
+    }
+}
+
+#[derive(Component)]
+pub struct ModeSlice;
+
+pub fn spawn_mode_slice(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // Unit grid in the XZ plane, scaled to the room when shown
+    let n = SLICE_RESOLUTION;
+    let mut positions = Vec::new();
+    for row in 0..=n {
+        for column in 0..=n {
+            positions.push([column as f32 / n as f32 - 0.5, 0.0, row as f32 / n as f32 - 0.5]);
+        }
+    }
+    let mut indices = Vec::new();
+    for row in 0..n as u32 {
+        for column in 0..n as u32 {
+            let i = row * (n as u32 + 1) + column;
+            let below = i + n as u32 + 1;
+            indices.extend([i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+    let count = positions.len();
+    let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, vec![[0.0, 0.0, 0.0, 0.0]; count])
+        .with_inserted_indices(Indices::U32(indices));
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color: Color::WHITE,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ModeSlice,
+    ));
+}
+
+pub fn update_room_modes(
+    room: Res<Room>,
+    medium: Res<Medium>,
+    mut view: ResMut<ModeView>,
+) {
+    if !room.is_changed() && !medium.is_changed() { return }
+    let modes = match room.shoebox {
+        Some(size) => room_modes(size, medium.speed_of_sound(), view.max_frequency),
+        None => Vec::new(),
+    };
+    let count = |kind| modes.iter().filter(|mode| mode.kind == kind).count();
+    info!(
+        "{} room modes up to {} Hz: {} axial, {} tangential, {} oblique",
+        modes.len(),
+        view.max_frequency,
+        count(ModeKind::Axial),
+        count(ModeKind::Tangential),
+        count(ModeKind::Oblique),
+    );
+    view.selected = view.selected.min(modes.len().saturating_sub(1));
+    view.modes = modes;
+}
+
+pub fn mode_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut view: ResMut<ModeView>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        view.visible = !view.visible;
+    }
+    if !view.visible { return }
+    let previous = view.selected;
+    if keyboard.just_pressed(KeyCode::Period) {
+        view.selected = (view.selected + 1).min(view.modes.len().saturating_sub(1));
+    }
+    if keyboard.just_pressed(KeyCode::Comma) {
+        view.selected = view.selected.saturating_sub(1);
+    }
+    if keyboard.pressed(KeyCode::PageUp) {
+        view.slice_height += 0.05;
+    }
+    if keyboard.pressed(KeyCode::PageDown) {
+        view.slice_height = (view.slice_height - 0.05).max(0.0);
+    }
+    if view.selected != previous || keyboard.just_pressed(KeyCode::KeyM) {
+        if let Some(mode) = view.current() {
+            info!(
+                "Mode ({}, {}, {}) {:?} at {:.1} Hz",
+                mode.indices.x, mode.indices.y, mode.indices.z, mode.kind, mode.frequency,
+            );
+        }
+    }
+}
+
+pub fn draw_mode_slice(
+    room: Res<Room>,
+    view: Res<ModeView>,
+    sim_time: Res<SimulationTime>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut slice_query: Query<(&Handle<Mesh>, &mut Transform, &mut Visibility), With<ModeSlice>>,
+) {
+    let Ok((handle, mut transform, mut visibility)) = slice_query.get_single_mut() else { return };
+    let (Some(size), Some(mode), true) = (room.shoebox, view.current(), view.visible) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+    let height = view.slice_height.min(size.y);
+    *transform = Transform::from_translation(room.origin + Vec3::Y * height)
+        .with_scale(Vec3::new(size.x, 1.0, size.z));
+
+    let Some(mesh) = meshes.get_mut(handle) else { return };
+    let swing = (TAU * mode.frequency * sim_time.elapsed).cos();
+    let n = SLICE_RESOLUTION;
+    let mut colors = Vec::with_capacity((n + 1) * (n + 1));
+    for row in 0..=n {
+        for column in 0..=n {
+            let local = Vec3::new(column as f32 / n as f32 * size.x, height, row as f32 / n as f32 * size.z);
+            let pressure = mode.pressure(size, local) * swing;
+            let strength = pressure.abs();
+            colors.push(if pressure >= 0.0 {
+                [1.0, 0.2, 0.1, strength * 0.8]
+            } else {
+                [0.1, 0.3, 1.0, strength * 0.8]
+            });
+        }
+    }
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Vec3 = Vec3::new(10.0, 5.0, 4.0);
+
+    #[test]
+    fn modes_are_listed_lowest_first_up_to_the_limit() {
+        let modes = room_modes(SIZE, 343.0, 40.0);
+        let found: Vec<(UVec3, ModeKind)> = modes.iter().map(|mode| (mode.indices, mode.kind)).collect();
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0], (UVec3::new(1, 0, 0), ModeKind::Axial));
+        assert!(found[1..3].contains(&(UVec3::new(2, 0, 0), ModeKind::Axial)));
+        assert!(found[1..3].contains(&(UVec3::new(0, 1, 0), ModeKind::Axial)));
+        assert_eq!(found[3], (UVec3::new(1, 1, 0), ModeKind::Tangential));
+        assert!((modes[0].frequency - 17.15).abs() < 1e-3);
+        assert!((modes[3].frequency - 343.0 / 2.0 * 0.05f32.sqrt()).abs() < 1e-3);
+        assert!(modes.windows(2).all(|pair| pair[0].frequency <= pair[1].frequency));
+    }
+
+    #[test]
+    fn oblique_modes_need_every_index() {
+        let modes = room_modes(SIZE, 343.0, 100.0);
+        assert!(modes.iter().all(|mode| mode.frequency <= 100.0));
+        let oblique = modes.iter().find(|mode| mode.kind == ModeKind::Oblique).unwrap();
+        assert!(oblique.indices.cmpgt(UVec3::ZERO).all());
+    }
+
+    #[test]
+    fn pressure_flips_across_the_room_for_odd_modes() {
+        let mode = room_modes(SIZE, 343.0, 40.0)[0];
+        assert!((mode.pressure(SIZE, Vec3::ZERO) - 1.0).abs() < 1e-6);
+        assert!((mode.pressure(SIZE, SIZE) + 1.0).abs() < 1e-6);
+        assert!(mode.pressure(SIZE, SIZE / 2.0).abs() < 1e-6);
+    }
+}
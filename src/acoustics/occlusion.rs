@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use crate::acoustics::room::Surface;
+use crate::audio::engine::{BandGains, BAND_CENTERS};
+use crate::extras::raycast::ray_sphere;
+
+// Sound passing through a solid obstacle rather than around it, in dB per octave band
+const TRANSMISSION_LOSS: [f32; 8] = [15.0, 18.0, 22.0, 27.0, 32.0, 37.0, 42.0, 45.0];
+// Keeps a path from being blocked by the surface it starts or ends on
+const ENDPOINT_MARGIN: f32 = 1e-3;
+
+/// Scene geometry that can stand between an emitter and the listener.
+pub enum Obstacle {
+    Sphere { entity: Entity, center: Vec3, radius: f32 },
+    Panel(Surface),
+}
+
+impl Obstacle {
+    fn blocks(&self, from: Vec3, to: Vec3) -> bool {
+        let length = from.distance(to);
+        if length <= 2.0 * ENDPOINT_MARGIN {
+            return false;
+        }
+        let direction = (to - from) / length;
+        match self {
+            Obstacle::Sphere { center, radius, .. } => ray_sphere(from, direction, *center, *radius)
+                .is_some_and(|distance| distance < length),
+            Obstacle::Panel(surface) => surface
+                .hit(from, direction)
+                .is_some_and(|distance| distance > ENDPOINT_MARGIN && distance < length - ENDPOINT_MARGIN),
+        }
+    }
+
+    /// The point on this obstacle's silhouette that gives the shortest way around it.
+    fn diffraction_edge(&self, from: Vec3, to: Vec3) -> Vec3 {
+        let detour = |point: Vec3| from.distance(point) + point.distance(to);
+        match self {
+            Obstacle::Sphere { center, radius, .. } => {
+                let axis = (to - from).normalize_or_zero();
+                let closest = from + axis * (*center - from).dot(axis);
+                let mut outward = (closest - *center).normalize_or_zero();
+                if outward == Vec3::ZERO {
+                    outward = axis.any_orthonormal_vector();
+                }
+                *center + outward * *radius
+            }
+            Obstacle::Panel(surface) => {
+                let corners = surface.corners();
+                (0..corners.len())
+                    .map(|i| closest_on_edge(corners[i], corners[(i + 1) % corners.len()], &detour))
+                    .min_by(|a, b| detour(*a).total_cmp(&detour(*b)))
+                    .unwrap()
+            }
+        }
+    }
+}
+
+// Minimises a convex cost along an edge by ternary search
+fn closest_on_edge(start: Vec3, end: Vec3, cost: &impl Fn(Vec3) -> f32) -> Vec3 {
+    let (mut low, mut high) = (0.0f32, 1.0f32);
+    for _ in 0..24 {
+        let a = low + (high - low) / 3.0;
+        let b = high - (high - low) / 3.0;
+        if cost(start.lerp(end, a)) < cost(start.lerp(end, b)) {
+            high = b;
+        } else {
+            low = a;
+        }
+    }
+    start.lerp(end, (low + high) / 2.0)
+}
+
+/// Per-band gain of a path bent over an edge with the given extra length (Maekawa),
+/// combined with what leaks through the obstacle.
+pub fn occlusion_gains(detour: f32, speed_of_sound: f32) -> BandGains {
+    let mut gains = [0.0; 8];
+    for ((gain, frequency), loss) in gains.iter_mut().zip(BAND_CENTERS).zip(TRANSMISSION_LOSS) {
+        let fresnel = 2.0 * detour * frequency / speed_of_sound;
+        let diffracted = 10f32.powf(-(10.0 * (3.0 + 20.0 * fresnel).log10()) / 20.0);
+        let transmitted = 10f32.powf(-loss / 20.0);
+        *gain = (diffracted * diffracted + transmitted * transmitted).sqrt();
+    }
+    BandGains(gains)
+}
+
+/// Reroutes every blocked segment of `points` over the obstacle in the way, returning
+/// the combined filtering. `source` is never treated as blocking its own sound.
+pub fn occlude_path(
+    points: &mut Vec<Vec3>,
+    obstacles: &[Obstacle],
+    source: Entity,
+    speed_of_sound: f32,
+) -> BandGains {
+    let mut bands = BandGains::UNITY;
+    let mut i = 0;
+    while i + 1 < points.len() {
+        let (from, to) = (points[i], points[i + 1]);
+        let blocker = obstacles.iter().find(|obstacle| {
+            !matches!(obstacle, Obstacle::Sphere { entity, .. } if *entity == source) && obstacle.blocks(from, to)
+        });
+        if let Some(obstacle) = blocker {
+            let edge = obstacle.diffraction_edge(from, to);
+            let detour = from.distance(edge) + edge.distance(to) - from.distance(to);
+            bands = bands * occlusion_gains(detour, speed_of_sound);
+            points.insert(i + 1, edge);
+            // One detour per segment, the halves around the edge are not checked again
+            i += 1;
+        }
+        i += 1;
+    }
+    bands
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use crate::acoustics::material::AcousticMaterial;
+use crate::acoustics::medium::Medium;
+use crate::acoustics::occlusion::{occlude_path, Obstacle};
+use crate::acoustics::room::{image_sources, reflection_path, ImageSource, Room, Surface, Wall};
+use crate::audio::engine::{AudioEngine, BandGains, Listener, Partial, Tap, Voice, SAMPLE_RATE};
+use crate::audio::microphone::Microphone;
+use crate::extras::components::AudioEmitter;
+use crate::extras::raycast::SphereBounds;
+
+// Paths shorter than this are not boosted any further
+const REFERENCE_DISTANCE: f32 = 1.0;
+
+#[derive(Clone, Debug)]
+pub struct SoundPath {
+    pub key: u64,
+    pub points: Vec<Vec3>, // emitter first, listener last
+    pub length: f32,
+    pub delay: f32, // in seconds
+    pub order: usize, // reflections, not counting diffracting edges
+    pub bands: BandGains, // surfaces, obstacles and air along the way
+}
+
+impl SoundPath {
+    pub fn gain(&self) -> f32 {
+        REFERENCE_DISTANCE / self.length.max(REFERENCE_DISTANCE)
+    }
+
+    pub fn tap(&self, listener: Listener) -> Tap {
+        Tap {
+            listener,
+            key: self.key,
+            delay: self.delay,
+            gain: self.gain(),
+            bands: self.bands,
+        }
+    }
+}
+
+/// Direct and early reflected paths from every emitter to the camera, refreshed each frame.
+#[derive(Resource, Default)]
+pub struct PropagationPaths {
+    pub emitters: Vec<(Entity, Vec<SoundPath>)>,
+}
+
+fn path_key(surfaces: &[usize]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    surfaces.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn path_length(points: &[Vec3]) -> f32 {
+    points.windows(2).map(|pair| pair[0].distance(pair[1])).sum()
+}
+
+/// Every path from `source`, through its image sources, to `listener`.
+fn trace_paths(
+    source: Vec3,
+    images: &[ImageSource],
+    listener: Vec3,
+    surfaces: &[Surface],
+    obstacles: &[Obstacle],
+    entity: Entity,
+    medium: &Medium,
+) -> Vec<SoundPath> {
+    let speed_of_sound = medium.speed_of_sound();
+    let mut routes = vec![(Vec::new(), vec![source, listener])];
+    for image in images {
+        let Some(points) = reflection_path(image, listener, surfaces) else { continue };
+        routes.push((image.chain.iter().map(|(index, _)| *index).collect(), points));
+    }
+
+    routes
+        .into_iter()
+        .map(|(order, mut points): (Vec<usize>, Vec<Vec3>)| {
+            let reflection = order
+                .iter()
+                .fold(BandGains::UNITY, |bands, &index| bands * surfaces[index].reflection);
+            let occlusion = occlude_path(&mut points, obstacles, entity, speed_of_sound);
+            let length = path_length(&points);
+            SoundPath {
+                key: path_key(&order),
+                length,
+                delay: length / speed_of_sound,
+                points,
+                order: order.len(),
+                bands: reflection * occlusion * medium.attenuation(length),
+            }
+        })
+        .collect()
+}
+
+pub fn update_propagation(
+    (room, medium): (Res<Room>, Res<Medium>),
+    walls: Query<(&Wall, &GlobalTransform, Option<&AcousticMaterial>)>,
+    spheres: Query<(Entity, &GlobalTransform, &SphereBounds)>,
+    listener_query: Query<&GlobalTransform, With<Camera3d>>,
+    microphones: Query<(Entity, &GlobalTransform), With<Microphone>>,
+    emitter_query: Query<(Entity, &AudioEmitter, &GlobalTransform)>,
+    (mut paths, engine): (ResMut<PropagationPaths>, Res<AudioEngine>),
+) {
+    let Ok(listener) = listener_query.get_single() else { return };
+    let listener = listener.translation();
+    let surfaces = room.surfaces(walls.iter());
+    let mut obstacles: Vec<Obstacle> = spheres
+        .iter()
+        .map(|(entity, transform, bounds)| {
+            let (scale, _, center) = transform.to_scale_rotation_translation();
+            Obstacle::Sphere { entity, center, radius: bounds.radius * scale.max_element() }
+        })
+        .collect();
+    obstacles.extend(surfaces.iter().filter(|s| s.two_sided).cloned().map(Obstacle::Panel));
+
+    paths.emitters.clear();
+    let mut voices = HashMap::new();
+    for (entity, emitter, transform) in emitter_query.iter() {
+        let source = transform.translation();
+        let images = image_sources(source, &surfaces, room.max_order);
+        let trace = |listener| trace_paths(source, &images, listener, &surfaces, &obstacles, entity, &medium);
+
+        let emitter_paths = trace(listener);
+        let mut taps: Vec<Tap> = emitter_paths.iter().map(|path| path.tap(Listener::Camera)).collect();
+        for (microphone, position) in microphones.iter() {
+            let heard = trace(position.translation());
+            taps.extend(heard.iter().map(|path| path.tap(Listener::Microphone(microphone))));
+        }
+
+        // Partials are relative, the emitter's amplitude sets the level of the whole tone
+        let total: f32 = emitter.partials.iter().map(|partial| partial.amplitude).sum();
+        let partials = emitter
+            .partials
+            .iter()
+            .map(|partial| Partial {
+                frequency: emitter.frequency * partial.ratio,
+                amplitude: emitter.amplitude * partial.amplitude / total.max(f32::EPSILON),
+                phase: emitter.phase + partial.phase,
+            })
+            .filter(|partial| partial.frequency < SAMPLE_RATE as f32 / 2.0)
+            .collect();
+        voices.insert(entity, Voice { partials, taps });
+        paths.emitters.push((entity, emitter_paths));
+    }
+    engine.submit(voices);
+}
+
+pub fn draw_reflections(
+    room: Res<Room>,
+    paths: Res<PropagationPaths>,
+    mut gizmos: Gizmos,
+) {
+    if !room.show_paths { return }
+    if let Some(size) = room.shoebox {
+        let center = room.origin + Vec3::Y * size.y / 2.0;
+        gizmos.cuboid(Transform::from_translation(center).with_scale(size), Color::GRAY);
+    }
+    for (_, emitter_paths) in &paths.emitters {
+        for path in emitter_paths {
+            let color = match path.order {
+                0 => Color::WHITE,
+                1 => Color::YELLOW,
+                2 => Color::ORANGE,
+                _ => Color::RED,
+            };
+            let strength = (path.gain() * path.bands.at(1000.0) * 4.0).clamp(0.1, 1.0);
+            // Paths that have lost their highs fade towards grey
+            let brightness = (path.bands.at(8000.0) / path.bands.at(1000.0).max(1e-6)).clamp(0.0, 1.0);
+            let color = Color::GRAY * (1.0 - brightness) + color * brightness;
+            gizmos.linestrip(path.points.iter().copied(), color.with_a(strength));
+        }
+    }
+}
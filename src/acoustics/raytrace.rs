@@ -0,0 +1,314 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use crate::acoustics::medium::Medium;
+use crate::acoustics::material::AcousticMaterial;
+use crate::acoustics::room::{Room, Surface, Wall};
+use crate::audio::engine::{AudioEngine, BAND_CENTERS, SAMPLE_RATE};
+use crate::audio::filter::Biquad;
+use crate::audio::impulse::LoadedImpulseResponse;
+use crate::extras::components::AudioEmitter;
+
+const BIN_SECONDS: f32 = 0.001;
+// Below this the image sources already cover the response
+const LATE_REVERB_START: f32 = 0.08;
+const LATE_REVERB_FADE: f32 = 0.01;
+
+#[derive(Resource)]
+pub struct RayTracing {
+    pub rays: usize,
+    pub max_time: f32,        // seconds of response to collect
+    pub receiver_radius: f32, // sphere around the listener that counts hits
+    pub seed: u64,
+    pub convolve: bool,
+}
+
+impl Default for RayTracing {
+    fn default() -> Self {
+        Self {
+            rays: 4000,
+            max_time: 3.0,
+            receiver_radius: 0.5,
+            seed: 1,
+            convolve: false,
+        }
+    }
+}
+
+/// Decay parameters of one energy-time curve. `None` when the decay is too short to fit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecayMetrics {
+    pub rt60: Option<f32>,
+    pub edt: Option<f32>,
+    pub c50: Option<f32>, // in dB
+    pub d50: Option<f32>,
+}
+
+#[derive(Resource, Default)]
+pub struct ReverbEstimate {
+    pub histogram: Vec<[f32; 8]>, // energy per 1 ms bin and octave band
+    pub impulse_response: Vec<f32>,
+    pub bands: [DecayMetrics; 8],
+    pub broadband: DecayMetrics, // mean of the 500 Hz and 1 kHz curves
+}
+
+impl ReverbEstimate {
+    /// The traced response from where the image sources stop, for convolving with the dry signal.
+    pub fn late_part(&self) -> Option<Vec<f32>> {
+        if self.impulse_response.is_empty() {
+            return None;
+        }
+        let onset = self
+            .histogram
+            .iter()
+            .position(|bin| bin.iter().any(|e| *e > 0.0))
+            .unwrap_or(0)
+            * (BIN_SECONDS * SAMPLE_RATE as f32) as usize;
+        Some(late_reverb(&self.impulse_response, onset))
+    }
+}
+
+/// Small xorshift generator so traces are repeatable for a given seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn unit_vector(&mut self) -> Vec3 {
+        let z = self.next_f32() * 2.0 - 1.0;
+        let angle = self.next_f32() * TAU;
+        let r = (1.0 - z * z).sqrt();
+        Vec3::new(r * angle.cos(), r * angle.sin(), z)
+    }
+
+    // Lambert distribution around `normal`
+    fn diffuse(&mut self, normal: Vec3) -> Vec3 {
+        let direction = (normal + self.unit_vector()).normalize_or_zero();
+        if direction == Vec3::ZERO { normal } else { direction }
+    }
+}
+
+/// Accumulates an energy-time histogram at `listener` for rays shot from each source.
+pub fn trace_energy(
+    sources: &[Vec3],
+    listener: Vec3,
+    surfaces: &[Surface],
+    settings: &RayTracing,
+    medium: &Medium,
+) -> Vec<[f32; 8]> {
+    let bins = (settings.max_time / BIN_SECONDS).ceil() as usize;
+    let mut histogram = vec![[0.0; 8]; bins];
+    if sources.is_empty() {
+        return histogram;
+    }
+    let mut rng = Rng::new(settings.seed);
+    let radius = settings.receiver_radius;
+    // Each crossing carries the share of a point source's intensity, 1 at 1 m
+    let deposit = 4.0 / (settings.rays as f32 * radius * radius) / sources.len() as f32;
+    let speed_of_sound = medium.speed_of_sound();
+    let max_distance = settings.max_time * speed_of_sound;
+    // Energy lost to the air per metre, per band
+    let air = BAND_CENTERS.map(|frequency| 10f32.powf(-medium.absorption(frequency) / 10.0));
+
+    for &source in sources {
+        for _ in 0..settings.rays {
+            let mut origin = source;
+            let mut direction = rng.unit_vector();
+            let mut energy = [1.0f32; 8];
+            let mut travelled = 0.0;
+
+            while travelled < max_distance {
+                let Some((distance, surface)) = surfaces
+                    .iter()
+                    .filter_map(|surface| surface.hit(origin, direction).map(|d| (d, surface)))
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                else {
+                    break;
+                };
+
+                let along = (listener - origin).dot(direction).clamp(0.0, distance);
+                if (origin + direction * along).distance(listener) <= radius {
+                    let bin = ((travelled + along) / speed_of_sound / BIN_SECONDS) as usize;
+                    if let Some(slot) = histogram.get_mut(bin) {
+                        for ((band, e), loss) in slot.iter_mut().zip(energy).zip(air) {
+                            *band += e * loss.powf(along) * deposit;
+                        }
+                    }
+                }
+
+                travelled += distance;
+                for ((e, r), loss) in energy.iter_mut().zip(surface.reflection.0).zip(air) {
+                    *e *= r * r * loss.powf(distance);
+                }
+                if energy.iter().all(|e| *e < 1e-6) {
+                    break;
+                }
+
+                let normal = if direction.dot(surface.normal) < 0.0 { surface.normal } else { -surface.normal };
+                origin += direction * distance + normal * 1e-4;
+                direction = if rng.next_f32() < surface.scattering {
+                    rng.diffuse(normal)
+                } else {
+                    direction - 2.0 * direction.dot(normal) * normal
+                };
+            }
+        }
+    }
+    histogram
+}
+
+/// RT60 (from T30), EDT, C50 and D50 of an energy-time curve sampled every `BIN_SECONDS`.
+pub fn decay_metrics(energy: &[f32]) -> DecayMetrics {
+    let Some(onset) = energy.iter().position(|e| *e > 0.0) else {
+        return DecayMetrics::default();
+    };
+    let energy = &energy[onset..];
+    let total: f32 = energy.iter().sum();
+
+    // Schroeder backward integration, in dB relative to the total
+    let mut remaining = total;
+    let decay: Vec<f32> = energy
+        .iter()
+        .map(|e| {
+            let level = 10.0 * (remaining / total).max(1e-12).log10();
+            remaining -= e;
+            level
+        })
+        .collect();
+
+    let early_bins = (0.05 / BIN_SECONDS) as usize;
+    let early: f32 = energy.iter().take(early_bins).sum();
+    let late = total - early;
+
+    DecayMetrics {
+        rt60: decay_slope(&decay, -5.0, -35.0).map(|slope| -60.0 / slope),
+        edt: decay_slope(&decay, 0.0, -10.0).map(|slope| -60.0 / slope),
+        c50: (late > 0.0).then(|| 10.0 * (early / late).log10()),
+        d50: Some(early / total),
+    }
+}
+
+// Least-squares slope in dB per second between two levels of a decay curve
+fn decay_slope(decay: &[f32], from: f32, to: f32) -> Option<f32> {
+    let points: Vec<(f32, f32)> = decay
+        .iter()
+        .enumerate()
+        .skip_while(|(_, level)| **level > from)
+        .take_while(|(_, level)| **level >= to)
+        .map(|(i, level)| (i as f32 * BIN_SECONDS, *level))
+        .collect();
+    let reached = decay.last().is_some_and(|level| *level < to);
+    if points.len() < 2 || !reached {
+        return None;
+    }
+    let n = points.len() as f32;
+    let mean_t = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_l = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let covariance: f32 = points.iter().map(|p| (p.0 - mean_t) * (p.1 - mean_l)).sum();
+    let variance: f32 = points.iter().map(|p| (p.0 - mean_t).powi(2)).sum();
+    let slope = covariance / variance;
+    (slope < 0.0).then_some(slope)
+}
+
+/// Turns a banded energy histogram into a pressure impulse response by shaping
+/// octave-filtered noise with each band's energy envelope.
+pub fn synthesize_impulse_response(histogram: &[[f32; 8]], seed: u64) -> Vec<f32> {
+    let per_bin = (BIN_SECONDS * SAMPLE_RATE as f32) as usize;
+    let mut response = vec![0.0; histogram.len() * per_bin];
+    let mut rng = Rng::new(seed);
+    let noise: Vec<f32> = (0..response.len())
+        .map(|_| if rng.next_f32() < 0.5 { -1.0 } else { 1.0 })
+        .collect();
+
+    for (band, center) in BAND_CENTERS.iter().enumerate() {
+        let mut filter = Biquad::band_pass(*center, SAMPLE_RATE as f32);
+        // An octave keeps about this share of white noise power
+        let bandwidth = 0.707 * center / (SAMPLE_RATE as f32 / 2.0);
+        let compensation = 1.0 / bandwidth.sqrt();
+        for (i, out) in response.iter_mut().enumerate() {
+            let energy = histogram[i / per_bin][band] / per_bin as f32;
+            *out += filter.process(noise[i] * energy.sqrt()) * compensation;
+        }
+    }
+    response
+}
+
+/// Only the late part of a response, faded in where the image sources stop.
+fn late_reverb(response: &[f32], onset: usize) -> Vec<f32> {
+    let start = onset + (LATE_REVERB_START * SAMPLE_RATE as f32) as usize;
+    let fade = (LATE_REVERB_FADE * SAMPLE_RATE as f32) as usize;
+    response
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let ramp = (i.saturating_sub(start) as f32 / fade as f32).min(1.0);
+            if i < start { 0.0 } else { sample * ramp }
+        })
+        .collect()
+}
+
+pub fn trace_reverb(
+    room: Res<Room>,
+    medium: Res<Medium>,
+    settings: Res<RayTracing>,
+    walls: Query<(&Wall, &GlobalTransform, Option<&AcousticMaterial>)>,
+    listener_query: Query<&GlobalTransform, With<Camera3d>>,
+    emitter_query: Query<&GlobalTransform, With<AudioEmitter>>,
+    mut estimate: ResMut<ReverbEstimate>,
+) {
+    let Ok(listener) = listener_query.get_single() else { return };
+    let sources: Vec<Vec3> = emitter_query.iter().map(|t| t.translation()).collect();
+    let surfaces = room.surfaces(walls.iter());
+    let histogram = trace_energy(&sources, listener.translation(), &surfaces, &settings, &medium);
+
+    let mut bands = [DecayMetrics::default(); 8];
+    for (band, metrics) in bands.iter_mut().enumerate() {
+        let curve: Vec<f32> = histogram.iter().map(|bin| bin[band]).collect();
+        *metrics = decay_metrics(&curve);
+    }
+    let mid: Vec<f32> = histogram.iter().map(|bin| (bin[3] + bin[4]) / 2.0).collect();
+    *estimate = ReverbEstimate {
+        impulse_response: synthesize_impulse_response(&histogram, settings.seed),
+        histogram,
+        bands,
+        broadband: decay_metrics(&mid),
+    };
+
+    let broadband = estimate.broadband;
+    info!(
+        "Reverb estimate: RT60 {:?} s, EDT {:?} s, C50 {:?} dB, D50 {:?}",
+        broadband.rt60, broadband.edt, broadband.c50, broadband.d50,
+    );
+    for (center, metrics) in BAND_CENTERS.iter().zip(&estimate.bands) {
+        debug!("{center} Hz: RT60 {:?} s, EDT {:?} s", metrics.rt60, metrics.edt);
+    }
+}
+
+pub fn apply_reverb(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<RayTracing>,
+    estimate: Res<ReverbEstimate>,
+    loaded: Res<LoadedImpulseResponse>,
+    engine: Res<AudioEngine>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        settings.convolve = !settings.convolve;
+    }
+    if !settings.is_changed() && !estimate.is_changed() && !loaded.is_changed() { return }
+
+    // A loaded response is a whole room on its own, so it replaces the traced tail
+    let impulse_response = match &loaded.samples {
+        Some(samples) => Some(samples.clone()),
+        None if settings.convolve => estimate.late_part(),
+        None => None,
+    };
+    engine.set_reverb(impulse_response);
+}
@@ -0,0 +1,397 @@
+use bevy::prelude::*;
+use crate::acoustics::material::{AcousticMaterial, MaterialLibrary};
+use crate::audio::engine::BandGains;
+use crate::scene::layout::SceneLayout;
+
+#[derive(Resource)]
+pub struct Room {
+    pub shoebox: Option<Vec3>, // interior width, height, depth
+    pub origin: Vec3,          // centre of the floor
+    // Floor, ceiling, -x, +x, -z, +z; `None` leaves that side open
+    pub faces: [Option<AcousticMaterial>; 6],
+    pub max_order: usize,
+    pub show_paths: bool,
+}
+
+// The scene layout's hall; its floor is left to the ground plane in the scene
+impl FromWorld for Room {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.get_resource::<SceneLayout>().map(|layout| layout.room.clone()).unwrap_or_default();
+        let library = world.resource::<MaterialLibrary>();
+        let material = |name: &String| {
+            let material = library.get(name).cloned();
+            if material.is_none() {
+                warn!("No material called {name:?}, leaving that side of the room open");
+            }
+            material
+        };
+        let [left, right, back, front] = &layout.walls;
+        Self {
+            shoebox: Some(Vec3::from(layout.size)),
+            origin: Vec3::ZERO,
+            faces: [
+                None,
+                material(&layout.ceiling),
+                material(left),
+                material(right),
+                material(back),
+                material(front),
+            ],
+            max_order: 2,
+            show_paths: true,
+        }
+    }
+}
+
+pub const MAX_REFLECTION_ORDER: usize = 4;
+
+/// A free-standing reflector. Its `AcousticMaterial`, if any, sets how it reflects.
+#[derive(Component, Clone, Debug)]
+pub enum Wall {
+    /// A rectangle spanning its local XZ plane like `Plane3d`.
+    Panel { half_size: Vec2 },
+    /// Any shape, as triangles in local space. Every triangle is a surface of its own,
+    /// so keep meshes coarse; reflections multiply with each one.
+    Mesh { triangles: Vec<[Vec3; 3]> },
+}
+
+impl Wall {
+    /// The triangles of a `TriangleList` mesh, indexed or not.
+    pub fn from_mesh(mesh: &Mesh) -> Option<Self> {
+        use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return None;
+        };
+        let indices: Vec<usize> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            Some(Indices::U32(indices)) => indices.iter().map(|i| *i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|corners| {
+                let corner = |i: usize| positions.get(corners[i]).copied().map(Vec3::from);
+                Some([corner(0)?, corner(1)?, corner(2)?])
+            })
+            .collect::<Option<_>>()?;
+        Some(Wall::Mesh { triangles })
+    }
+}
+
+/// The outline of a `Surface` within its plane.
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Rectangle { axes: [Vec3; 2], half_size: Vec2 },
+    // Wound so that `normal` follows the right-hand rule
+    Triangle([Vec3; 3]),
+}
+
+/// A bounded reflecting polygon in world space.
+#[derive(Clone, Debug)]
+pub struct Surface {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub shape: Shape,
+    pub reflection: BandGains,
+    pub scattering: f32, // mid-band, used to pick specular or diffuse bounces
+    pub two_sided: bool,
+}
+
+impl Surface {
+    /// A triangle facing along its winding, or `None` if it has no area.
+    pub fn triangle(corners: [Vec3; 3], reflection: BandGains, scattering: f32, two_sided: bool) -> Option<Self> {
+        let [a, b, c] = corners;
+        let normal = (b - a).cross(c - a).try_normalize()?;
+        Some(Self {
+            center: (a + b + c) / 3.0,
+            normal,
+            shape: Shape::Triangle(corners),
+            reflection,
+            scattering,
+            two_sided,
+        })
+    }
+
+    /// The outline's corners, in order around it.
+    pub fn corners(&self) -> Vec<Vec3> {
+        match &self.shape {
+            Shape::Rectangle { axes: [u, v], half_size } => vec![
+                self.center - *u * half_size.x - *v * half_size.y,
+                self.center + *u * half_size.x - *v * half_size.y,
+                self.center + *u * half_size.x + *v * half_size.y,
+                self.center - *u * half_size.x + *v * half_size.y,
+            ],
+            Shape::Triangle(corners) => corners.to_vec(),
+        }
+    }
+
+    // Whether a point in this surface's plane is within its outline
+    fn contains(&self, point: Vec3) -> bool {
+        match &self.shape {
+            Shape::Rectangle { axes: [u, v], half_size } => {
+                let local = point - self.center;
+                local.dot(*u).abs() <= half_size.x && local.dot(*v).abs() <= half_size.y
+            }
+            Shape::Triangle(corners) => (0..3).all(|i| {
+                let (start, end) = (corners[i], corners[(i + 1) % 3]);
+                (end - start).cross(point - start).dot(self.normal) >= -1e-6
+            }),
+        }
+    }
+
+    pub fn mirror(&self, point: Vec3) -> Vec3 {
+        point - 2.0 * (point - self.center).dot(self.normal) * self.normal
+    }
+
+    /// Distance along a unit `direction` to where a ray hits this surface.
+    pub fn hit(&self, origin: Vec3, direction: Vec3) -> Option<f32> {
+        let facing = direction.dot(self.normal);
+        if facing.abs() < 1e-6 {
+            return None;
+        }
+        let distance = (self.center - origin).dot(self.normal) / facing;
+        if distance <= 1e-4 {
+            return None;
+        }
+        self.contains(origin + direction * distance).then_some(distance)
+    }
+
+    /// Where the segment `from -> to` crosses this surface, if it does within its bounds.
+    pub fn crossing(&self, from: Vec3, to: Vec3) -> Option<Vec3> {
+        let d_from = (from - self.center).dot(self.normal);
+        let d_to = (to - self.center).dot(self.normal);
+        if d_from * d_to >= 0.0 || (!self.two_sided && d_from < 0.0) {
+            return None;
+        }
+        let hit = from + (to - from) * (d_from / (d_from - d_to));
+        self.contains(hit).then_some(hit)
+    }
+}
+
+impl Room {
+    pub fn surfaces<'a>(
+        &self,
+        walls: impl Iterator<Item = (&'a Wall, &'a GlobalTransform, Option<&'a AcousticMaterial>)>,
+    ) -> Vec<Surface> {
+        let mut surfaces = Vec::new();
+        if let Some(size) = self.shoebox {
+            let half = size / 2.0;
+            let center = self.origin + Vec3::Y * half.y;
+            for (face, (normal, u, v, extent)) in self.faces.iter().zip([
+                (Vec3::Y, Vec3::X, Vec3::Z, Vec2::new(half.x, half.z)),
+                (Vec3::NEG_Y, Vec3::X, Vec3::Z, Vec2::new(half.x, half.z)),
+                (Vec3::X, Vec3::Y, Vec3::Z, Vec2::new(half.y, half.z)),
+                (Vec3::NEG_X, Vec3::Y, Vec3::Z, Vec2::new(half.y, half.z)),
+                (Vec3::Z, Vec3::X, Vec3::Y, Vec2::new(half.x, half.y)),
+                (Vec3::NEG_Z, Vec3::X, Vec3::Y, Vec2::new(half.x, half.y)),
+            ]) {
+                let Some(material) = face else { continue };
+                surfaces.push(Surface {
+                    // Normals face into the room
+                    center: center - normal * half.dot(normal.abs()),
+                    normal,
+                    shape: Shape::Rectangle { axes: [u, v], half_size: extent },
+                    reflection: material.reflection(),
+                    scattering: material.mid_scattering(),
+                    two_sided: false,
+                });
+            }
+        }
+        for (wall, transform, material) in walls {
+            let material = material.cloned().unwrap_or_default();
+            match wall {
+                Wall::Panel { half_size } => {
+                    let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+                    surfaces.push(Surface {
+                        center: translation,
+                        normal: rotation * Vec3::Y,
+                        shape: Shape::Rectangle {
+                            axes: [rotation * Vec3::X, rotation * Vec3::Z],
+                            half_size: *half_size * Vec2::new(scale.x, scale.z),
+                        },
+                        reflection: material.reflection(),
+                        scattering: material.mid_scattering(),
+                        two_sided: true,
+                    });
+                }
+                Wall::Mesh { triangles } => {
+                    surfaces.extend(triangles.iter().filter_map(|corners| {
+                        let corners = corners.map(|corner| transform.transform_point(corner));
+                        Surface::triangle(corners, material.reflection(), material.mid_scattering(), true)
+                    }));
+                }
+            }
+        }
+        surfaces
+    }
+}
+
+/// A mirrored copy of a source, remembering which surfaces produced it.
+#[derive(Clone, Debug)]
+pub struct ImageSource {
+    pub position: Vec3,
+    // (surface index, image position before reflecting off it), innermost first
+    pub chain: Vec<(usize, Vec3)>,
+}
+
+/// All image sources of `source` up to `max_order`, without any visibility check.
+pub fn image_sources(source: Vec3, surfaces: &[Surface], max_order: usize) -> Vec<ImageSource> {
+    let mut images = Vec::new();
+    let mut frontier = vec![ImageSource { position: source, chain: Vec::new() }];
+    for _ in 0..max_order {
+        let mut next = Vec::new();
+        for image in &frontier {
+            let last = image.chain.last().map(|(index, _)| *index);
+            for (index, surface) in surfaces.iter().enumerate() {
+                if Some(index) == last {
+                    continue;
+                }
+                let side = (image.position - surface.center).dot(surface.normal);
+                if side == 0.0 || (!surface.two_sided && side < 0.0) {
+                    continue;
+                }
+                let mut chain = image.chain.clone();
+                chain.push((index, image.position));
+                next.push(ImageSource { position: surface.mirror(image.position), chain });
+            }
+        }
+        images.extend(next.iter().cloned());
+        frontier = next;
+    }
+    images
+}
+
+/// Traces an image back from the listener, returning the path emitter-first if every
+/// reflection lands on its surface.
+pub fn reflection_path(image: &ImageSource, listener: Vec3, surfaces: &[Surface]) -> Option<Vec<Vec3>> {
+    let mut points = vec![listener];
+    let mut target = image.position;
+    for &(index, previous) in image.chain.iter().rev() {
+        let from = *points.last().unwrap();
+        let hit = surfaces[index].crossing(from, target)?;
+        points.push(hit);
+        target = previous;
+    }
+    points.push(target);
+    points.reverse();
+    Some(points)
+}
+
+pub fn room_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut room: ResMut<Room>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyR) {
+        room.show_paths = !room.show_paths;
+    }
+    if keyboard.just_pressed(KeyCode::BracketRight) {
+        room.max_order = (room.max_order + 1).min(MAX_REFLECTION_ORDER);
+    }
+    if keyboard.just_pressed(KeyCode::BracketLeft) {
+        room.max_order = room.max_order.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 10 m wide, 4 m high and 8 m deep, every side reflecting
+    fn shoebox() -> Vec<Surface> {
+        let room = Room {
+            shoebox: Some(Vec3::new(10.0, 4.0, 8.0)),
+            origin: Vec3::ZERO,
+            faces: std::array::from_fn(|_| Some(AcousticMaterial::default())),
+            max_order: 2,
+            show_paths: false,
+        };
+        room.surfaces(std::iter::empty())
+    }
+
+    fn surfaces_of(wall: Wall, transform: Transform) -> Vec<Surface> {
+        let room = Room { shoebox: None, origin: Vec3::ZERO, faces: default(), max_order: 1, show_paths: false };
+        room.surfaces(std::iter::once((&wall, &GlobalTransform::from(transform), None)))
+    }
+
+    fn length(path: &[Vec3]) -> f32 {
+        path.windows(2).map(|pair| pair[0].distance(pair[1])).sum()
+    }
+
+    #[test]
+    fn first_order_images_mirror_the_source_in_each_side() {
+        let images = image_sources(Vec3::new(1.0, 1.0, 0.0), &shoebox(), 1);
+        let positions: Vec<Vec3> = images.iter().map(|image| image.position).collect();
+        assert_eq!(
+            positions,
+            [
+                Vec3::new(1.0, -1.0, 0.0),
+                Vec3::new(1.0, 7.0, 0.0),
+                Vec3::new(-11.0, 1.0, 0.0),
+                Vec3::new(9.0, 1.0, 0.0),
+                Vec3::new(1.0, 1.0, -8.0),
+                Vec3::new(1.0, 1.0, 8.0),
+            ]
+        );
+        // Every image of the next order comes off a different side than its parent
+        assert_eq!(image_sources(Vec3::new(1.0, 1.0, 0.0), &shoebox(), 2).len(), 6 + 6 * 5);
+    }
+
+    #[test]
+    fn reflection_paths_are_as_long_as_the_image_is_far() {
+        let (source, listener) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(-2.0, 2.0, 1.0));
+        let surfaces = shoebox();
+        let images = image_sources(source, &surfaces, 2);
+
+        let floor = reflection_path(&images[0], listener, &surfaces).unwrap();
+        assert_eq!(floor.len(), 3);
+        assert_eq!(floor[0], source);
+        assert_eq!(floor[2], listener);
+        assert!(floor[1].distance(Vec3::new(0.0, 0.0, 1.0 / 3.0)) < 1e-5);
+        assert!((length(&floor) - 19f32.sqrt()).abs() < 1e-5);
+
+        // Floor then ceiling
+        let image = images.iter().find(|image| image.chain.iter().map(|(index, _)| *index).eq([0, 1])).unwrap();
+        assert_eq!(image.position, Vec3::new(1.0, 9.0, 0.0));
+        let path = reflection_path(image, listener, &surfaces).unwrap();
+        assert_eq!(path.len(), 4);
+        assert!(path[1].y.abs() < 1e-5 && (path[2].y - 4.0).abs() < 1e-5);
+        assert!((length(&path) - 59f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mesh_walls_reflect_only_within_their_triangles() {
+        let triangle = [Vec3::ZERO, Vec3::new(0.0, 0.0, 2.0), Vec3::new(2.0, 0.0, 0.0)];
+        let surfaces = surfaces_of(Wall::Mesh { triangles: vec![triangle] }, Transform::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(surfaces.len(), 1);
+        assert_eq!(surfaces[0].normal, Vec3::Y);
+
+        let source = Vec3::new(1.2, 1.0, 0.2);
+        let images = image_sources(source, &surfaces, 1);
+        assert_eq!(images[0].position, Vec3::new(1.2, -1.0, 0.2));
+        let inside = reflection_path(&images[0], Vec3::new(1.6, 1.0, 0.6), &surfaces).unwrap();
+        assert!(inside[1].distance(Vec3::new(1.4, 0.0, 0.4)) < 1e-5);
+        // Past the slanted edge, where the rectangle around it would still reflect
+        assert!(reflection_path(&images[0], Vec3::new(3.0, 1.0, 2.0), &surfaces).is_none());
+
+        assert_eq!(surfaces[0].hit(Vec3::new(1.5, 1.0, 0.5), Vec3::NEG_Y), Some(1.0));
+        assert_eq!(surfaces[0].hit(Vec3::new(2.5, 1.0, 1.5), Vec3::NEG_Y), None);
+    }
+
+    #[test]
+    fn a_meshed_plane_reflects_like_a_panel() {
+        let wall = Wall::from_mesh(&Mesh::from(Plane3d::default().mesh().size(2.0, 2.0))).unwrap();
+        let Wall::Mesh { triangles } = &wall else { panic!("not a mesh") };
+        assert_eq!(triangles.len(), 2);
+
+        let transform = Transform::from_xyz(0.0, 1.0, 0.0).with_scale(Vec3::splat(2.0));
+        let mesh = surfaces_of(wall, transform);
+        let panel = surfaces_of(Wall::Panel { half_size: Vec2::ONE }, transform);
+        for origin in [Vec3::new(1.9, 3.0, -1.9), Vec3::new(-0.5, 3.0, 1.5), Vec3::new(2.1, 3.0, 0.0)] {
+            let hit = |surfaces: &[Surface]| surfaces.iter().find_map(|surface| surface.hit(origin, Vec3::NEG_Y));
+            assert_eq!(hit(&mesh), hit(&panel), "from {origin}");
+        }
+    }
+}
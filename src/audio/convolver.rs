@@ -0,0 +1,128 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Uniformly partitioned overlap-save convolution, one block of `block_size` samples at a time.
+pub struct Convolver {
+    block_size: usize,
+    forward: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    partitions: Vec<Vec<Complex<f32>>>,
+    history: VecDeque<Vec<Complex<f32>>>,
+    previous: Vec<f32>,
+    accumulator: Vec<Complex<f32>>,
+    // Owned so the transforms never allocate on the audio thread
+    scratch: Vec<Complex<f32>>,
+}
+
+impl Convolver {
+    pub fn new(impulse_response: &[f32], block_size: usize) -> Self {
+        let size = block_size * 2;
+        let mut planner = FftPlanner::new();
+        let forward = planner.plan_fft_forward(size);
+        let inverse = planner.plan_fft_inverse(size);
+
+        let partitions: Vec<Vec<Complex<f32>>> = impulse_response
+            .chunks(block_size)
+            .map(|chunk| {
+                let mut spectrum = vec![Complex::default(); size];
+                for (bin, sample) in spectrum.iter_mut().zip(chunk) {
+                    bin.re = *sample;
+                }
+                forward.process(&mut spectrum);
+                spectrum
+            })
+            .collect();
+        let history = (0..partitions.len())
+            .map(|_| vec![Complex::default(); size])
+            .collect();
+        let scratch = forward.get_inplace_scratch_len().max(inverse.get_inplace_scratch_len());
+
+        Self {
+            block_size,
+            forward,
+            inverse,
+            partitions,
+            history,
+            previous: vec![0.0; block_size],
+            accumulator: vec![Complex::default(); size],
+            scratch: vec![Complex::default(); scratch],
+        }
+    }
+
+    /// Convolves `input` (exactly one block) and adds the result to `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut [f32]) {
+        debug_assert_eq!(input.len(), self.block_size);
+        if self.partitions.is_empty() {
+            return;
+        }
+        let size = self.block_size * 2;
+
+        let mut spectrum = self.history.pop_back().unwrap();
+        for (bin, sample) in spectrum.iter_mut().zip(self.previous.iter().chain(input)) {
+            *bin = Complex::new(*sample, 0.0);
+        }
+        self.forward.process_with_scratch(&mut spectrum, &mut self.scratch);
+        self.history.push_front(spectrum);
+        self.previous.copy_from_slice(input);
+
+        self.accumulator.fill(Complex::default());
+        for (spectrum, partition) in self.history.iter().zip(&self.partitions) {
+            for ((acc, x), h) in self.accumulator.iter_mut().zip(spectrum).zip(partition) {
+                *acc += x * h;
+            }
+        }
+        self.inverse.process_with_scratch(&mut self.accumulator, &mut self.scratch);
+
+        let scale = 1.0 / size as f32;
+        for (sample, bin) in out.iter_mut().zip(&self.accumulator[self.block_size..]) {
+            *sample += bin.re * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fixed linear congruential sequence in -1..1, so failures reproduce
+    fn noise(len: usize, mut state: u32) -> Vec<f32> {
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_direct_convolution() {
+        let block_size = 64;
+        // Not a whole number of partitions, and longer than the input is in blocks
+        let impulse_response = noise(300, 1);
+        let input = noise(block_size * 8, 2);
+
+        let mut convolver = Convolver::new(&impulse_response, block_size);
+        let mut output = vec![0.5; input.len()];
+        for (input, out) in input.chunks(block_size).zip(output.chunks_mut(block_size)) {
+            convolver.process(input, out);
+        }
+
+        for (n, sample) in output.iter().enumerate() {
+            let direct: f32 = (0..=n.min(impulse_response.len() - 1))
+                .map(|k| impulse_response[k] * input[n - k])
+                .sum();
+            // Added to what was already there
+            assert!((sample - 0.5 - direct).abs() < 1e-3, "sample {n}: {} against {direct}", sample - 0.5);
+        }
+    }
+
+    #[test]
+    fn an_empty_response_adds_nothing() {
+        let mut convolver = Convolver::new(&[], 16);
+        let mut out = vec![0.25; 16];
+        convolver.process(&noise(16, 3), &mut out);
+        assert!(out.iter().all(|sample| *sample == 0.25));
+    }
+}
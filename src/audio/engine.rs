@@ -0,0 +1,608 @@
+use bevy::prelude::*;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::SampleRate;
+use rodio::{OutputStream, OutputStreamHandle, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Mul;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::audio::convolver::Convolver;
+use crate::audio::offline::OfflineAudio;
+
+pub const SAMPLE_RATE: u32 = 48_000;
+// Divides a simulation tick exactly, so fixed steps line up with blocks
+pub const BLOCK_SIZE: usize = 400;
+// Longest path a voice can replay, about 340 m of travel
+const HISTORY_SECONDS: f32 = 1.0;
+// How much of every listener's signal is kept for meters and saving
+pub const RECORD_SECONDS: f32 = 10.0;
+
+pub const BAND_CENTERS: [f32; 8] = [63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0];
+
+/// Octave-band amplitude gains, used for anything frequency dependent along a path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BandGains(pub [f32; 8]);
+
+impl BandGains {
+    pub const UNITY: Self = Self([1.0; 8]);
+
+    /// Gain at an arbitrary frequency, interpolated on a log-frequency axis.
+    pub fn at(&self, frequency: f32) -> f32 {
+        let octave = (frequency.max(1.0) / BAND_CENTERS[0]).log2().clamp(0.0, 7.0);
+        let index = octave.floor() as usize;
+        if index >= 7 {
+            return self.0[7];
+        }
+        let t = octave - index as f32;
+        self.0[index] * (1.0 - t) + self.0[index + 1] * t
+    }
+}
+
+impl Mul for BandGains {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let mut out = self.0;
+        for (gain, other) in out.iter_mut().zip(rhs.0) {
+            *gain *= other;
+        }
+        Self(out)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Partial {
+    pub frequency: f32, // in Hz
+    pub amplitude: f32,
+    pub phase: f32,
+}
+
+/// Where a tap is heard: the camera or one of the virtual microphones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Listener {
+    #[default]
+    Camera,
+    Microphone(Entity),
+}
+
+/// A signal the mixer keeps a recent capture of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Signal {
+    Listener(Listener),
+    // What reaches the speakers, after reverb
+    Master,
+    // One emitter's share of the monitored listener
+    Emitter(Entity),
+}
+
+impl From<Listener> for Signal {
+    fn from(listener: Listener) -> Self {
+        Self::Listener(listener)
+    }
+}
+
+/// One propagation path from a voice to a listener.
+#[derive(Clone, Copy, Debug)]
+pub struct Tap {
+    pub listener: Listener,
+    // Stable across updates so a moving path glides instead of restarting
+    pub key: u64,
+    pub delay: f32, // in seconds
+    pub gain: f32,
+    pub bands: BandGains,
+}
+
+/// Everything the mixer needs to render one `AudioEmitter` at every listener.
+#[derive(Clone, Debug, Default)]
+pub struct Voice {
+    pub partials: Vec<Partial>,
+    pub taps: Vec<Tap>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct MixParams {
+    voices: HashMap<Entity, Voice>,
+    // The listener that feeds the speakers
+    monitor: Listener,
+}
+
+// An impulse response and the convolver already planned for it
+type PreparedReverb = (Arc<Vec<f32>>, Convolver);
+
+/// The shared late reverb, fed with the dry sum of every voice. Each mixer gets its own
+/// convolver, prepared on the ECS side and sent over to be swapped in.
+#[derive(Default)]
+struct Reverb {
+    impulse_response: Option<Arc<Vec<f32>>>,
+    mixers: Vec<Sender<Option<PreparedReverb>>>,
+}
+
+impl Reverb {
+    fn convolver(&self) -> Option<PreparedReverb> {
+        let impulse_response = self.impulse_response.clone()?;
+        let convolver = Convolver::new(&impulse_response, BLOCK_SIZE);
+        Some((impulse_response, convolver))
+    }
+}
+
+/// The last `RECORD_SECONDS` of every signal, written by the audio thread.
+#[derive(Default)]
+struct Captures {
+    // Samples rendered since the stream started, the end of every capture
+    rendered: u64,
+    // Blocks written, each signal keeps the last one it was part of
+    blocks: u64,
+    signals: HashMap<Signal, (u64, VecDeque<f32>)>,
+}
+
+/// Handle the ECS uses to hand voices to the audio thread.
+#[derive(Resource, Clone, Default)]
+pub struct AudioEngine {
+    // The latest snapshot, replaced whole so the audio thread never copies it
+    params: Arc<Mutex<Arc<MixParams>>>,
+    reverb: Arc<Mutex<Reverb>>,
+    captures: Arc<Mutex<Captures>>,
+}
+
+impl AudioEngine {
+    fn update(&self, change: impl FnOnce(&mut MixParams)) {
+        let mut params = self.params.lock().unwrap();
+        // Copies the snapshot here if a mixer still holds it, never on the audio thread
+        change(Arc::make_mut(&mut params));
+    }
+
+    pub fn submit(&self, voices: HashMap<Entity, Voice>) {
+        self.update(|params| params.voices = voices);
+    }
+
+    pub fn set_reverb(&self, impulse_response: Option<Vec<f32>>) {
+        let mut reverb = self.reverb.lock().unwrap();
+        reverb.impulse_response = impulse_response.map(Arc::new);
+        // Planning and transforming the response is too slow for the audio thread
+        let mixers = std::mem::take(&mut reverb.mixers);
+        reverb.mixers = mixers.into_iter().filter(|mixer| mixer.send(reverb.convolver()).is_ok()).collect();
+    }
+
+    pub fn set_monitor(&self, listener: Listener) {
+        self.update(|params| params.monitor = listener);
+    }
+
+    /// The latest `len` samples of `signal`, oldest first.
+    pub fn recent(&self, signal: impl Into<Signal>, len: usize) -> Option<Vec<f32>> {
+        let captures = self.captures.lock().unwrap();
+        let (_, capture) = captures.signals.get(&signal.into())?;
+        Some(capture.iter().skip(capture.len().saturating_sub(len)).copied().collect())
+    }
+
+    /// Everything of `signal` from sample `position` of the stream on, as much as is
+    /// still held, and the position it ends at.
+    pub fn since(&self, signal: impl Into<Signal>, position: u64) -> Option<(Vec<f32>, u64)> {
+        let captures = self.captures.lock().unwrap();
+        let (_, capture) = captures.signals.get(&signal.into())?;
+        let start = captures.rendered - capture.len() as u64;
+        let skip = position.saturating_sub(start) as usize;
+        Some((capture.iter().skip(skip).copied().collect(), captures.rendered))
+    }
+
+    /// Everything still held of `signal`, up to `RECORD_SECONDS`.
+    pub fn recording(&self, signal: impl Into<Signal>) -> Option<Vec<f32>> {
+        let captures = self.captures.lock().unwrap();
+        captures.signals.get(&signal.into()).map(|(_, capture)| capture.iter().copied().collect())
+    }
+
+    pub fn mixer(&self) -> Mixer {
+        let mut reverb = self.reverb.lock().unwrap();
+        let (sender, receiver) = channel();
+        reverb.mixers.push(sender);
+        Mixer {
+            params: self.params.clone(),
+            snapshot: self.params.lock().unwrap().clone(),
+            captures: self.captures.clone(),
+            voices: BTreeMap::new(),
+            reverb: reverb.convolver(),
+            reverb_updates: Mutex::new(receiver),
+            send: vec![0.0; BLOCK_SIZE],
+            outputs: BTreeMap::from([(Listener::Camera, vec![0.0; BLOCK_SIZE])]),
+            dropped: 0,
+        }
+    }
+}
+
+/// Which device plays the monitored listener and at what rate. The mix is always rendered
+/// at `SAMPLE_RATE`; rodio converts it to the device rate.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>, // the system default if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>, // the device's own if unset
+}
+
+/// Names of every output device, for picking one in `AudioSettings`.
+pub fn output_device_names() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Keeps the output stream alive; dropping it silences the app.
+pub struct AudioOutput {
+    _stream: OutputStream,
+}
+
+impl AudioOutput {
+    pub fn open(engine: &AudioEngine, settings: &AudioSettings) -> Option<Self> {
+        let (stream, handle) = match Self::stream(settings) {
+            Ok(output) => output,
+            Err(err) => {
+                warn!("No audio output available, running silent: {err}");
+                return None;
+            }
+        };
+        if let Err(err) = handle.play_raw(ListenerSource::new(engine.mixer())) {
+            warn!("Could not start listener mix: {err}");
+            return None;
+        }
+        Some(Self { _stream: stream })
+    }
+
+    fn stream(settings: &AudioSettings) -> Result<(OutputStream, OutputStreamHandle), String> {
+        let host = cpal::default_host();
+        let device = match &settings.device {
+            Some(name) => host
+                .output_devices()
+                .map_err(|err| err.to_string())?
+                .find(|device| device.name().is_ok_and(|device_name| &device_name == name))
+                .ok_or_else(|| format!("no output device called {name}, there is {}", output_device_names().join(", ")))?,
+            None => host.default_output_device().ok_or("no default output device")?,
+        };
+        let stream = match settings.sample_rate {
+            Some(rate) => {
+                let config = device
+                    .supported_output_configs()
+                    .map_err(|err| err.to_string())?
+                    .find(|config| (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&rate))
+                    .ok_or_else(|| format!("the output device cannot play at {rate} Hz"))?
+                    .with_sample_rate(SampleRate(rate));
+                OutputStream::try_from_device_config(&device, config)
+            }
+            None => OutputStream::try_from_device(&device),
+        };
+        stream.map_err(|err| err.to_string())
+    }
+}
+
+/// Plays the monitored listener on the device in `AudioSettings`, unless `OfflineAudio`
+/// renders it.
+pub fn open_audio_output(world: &mut World) {
+    if world.contains_resource::<OfflineAudio>() { return }
+    let settings = world.get_resource::<AudioSettings>().cloned().unwrap_or_default();
+    let Some(engine) = world.get_resource::<AudioEngine>() else { return };
+    if let Some(output) = AudioOutput::open(engine, &settings) {
+        world.insert_non_send_resource(output);
+    }
+}
+
+struct TapState {
+    listener: Listener,
+    delay: f32, // in samples
+    gain: f32,
+    coeff: f32,
+    lowpass: f32,
+    target: (f32, f32, f32),
+}
+
+impl TapState {
+    fn target_for(tap: &Tap) -> (f32, f32, f32) {
+        // Broadband gain from the mid band, high-frequency loss as a one-pole lowpass
+        // whose response at 4 kHz matches the band gains.
+        let mid = tap.bands.at(1000.0);
+        let ratio = if mid > 0.0 { tap.bands.at(4000.0) / mid } else { 1.0 };
+        let coeff = if ratio >= 0.999 {
+            1.0
+        } else {
+            let cutoff = 4000.0 / (1.0 / (ratio * ratio).max(1e-6) - 1.0).sqrt();
+            1.0 - (-std::f32::consts::TAU * cutoff / SAMPLE_RATE as f32).exp()
+        };
+        (tap.delay * SAMPLE_RATE as f32, tap.gain * mid, coeff)
+    }
+}
+
+struct VoiceState {
+    phases: Vec<f64>,
+    history: Vec<f32>,
+    // Ordered so taps are always summed the same way and a mix is reproducible
+    taps: BTreeMap<(Listener, u64), TapState>,
+    // The last block heard at each listener, kept to be refilled
+    heard: BTreeMap<Listener, Vec<f32>>,
+}
+
+impl VoiceState {
+    fn new() -> Self {
+        let len = (HISTORY_SECONDS * SAMPLE_RATE as f32) as usize + BLOCK_SIZE + 2;
+        Self {
+            phases: Vec::new(),
+            history: vec![0.0; len],
+            taps: BTreeMap::new(),
+            heard: BTreeMap::new(),
+        }
+    }
+
+    fn retarget(&mut self, voice: Option<&Voice>) {
+        for tap in self.taps.values_mut() {
+            tap.target = (tap.delay, 0.0, tap.coeff);
+        }
+        let Some(voice) = voice else { return };
+        let max_delay = HISTORY_SECONDS * SAMPLE_RATE as f32;
+        for tap in &voice.taps {
+            let target = TapState::target_for(tap);
+            if target.0 >= max_delay {
+                continue;
+            }
+            self.taps
+                .entry((tap.listener, tap.key))
+                .and_modify(|state| state.target = target)
+                .or_insert(TapState {
+                    listener: tap.listener,
+                    delay: target.0,
+                    gain: 0.0,
+                    coeff: target.2,
+                    lowpass: 0.0,
+                    target,
+                });
+        }
+    }
+
+    /// Writes the next block of dry signal, from the voice's partials, into history and `send`.
+    fn synthesize(&mut self, partials: &[Partial], start: u64, send: &mut [f32]) {
+        let len = self.history.len();
+        self.phases.resize(partials.len(), 0.0);
+        for (i, send) in send.iter_mut().enumerate() {
+            let mut dry = 0.0;
+            for (partial, phase) in partials.iter().zip(self.phases.iter_mut()) {
+                dry += partial.amplitude * (*phase as f32 * std::f32::consts::TAU + partial.phase).sin();
+                *phase = (*phase + partial.frequency as f64 / SAMPLE_RATE as f64).fract();
+            }
+            self.history[(start as usize + i) % len] = dry;
+            *send += dry;
+        }
+    }
+
+    /// Renders every tap's view of the history into its listener's block in `heard`,
+    /// gliding towards the tap targets.
+    fn render_taps(&mut self, start: u64, len: usize) {
+        self.heard.retain(|listener, _| self.taps.values().any(|tap| tap.listener == *listener));
+        for block in self.heard.values_mut() {
+            block.fill(0.0);
+        }
+        let steps = len as f32;
+        for tap in self.taps.values_mut() {
+            let (delay, gain, coeff) = tap.target;
+            let out = self.heard.entry(tap.listener).or_insert_with(|| vec![0.0; len]);
+            for (i, sample) in out.iter_mut().enumerate() {
+                let t = (i + 1) as f32 / steps;
+                let d = tap.delay + (delay - tap.delay) * t;
+                let g = tap.gain + (gain - tap.gain) * t;
+                let position = (start + i as u64) as f64 - d as f64;
+                let wet = read_history(&self.history, position);
+                tap.lowpass += coeff * (wet - tap.lowpass);
+                *sample += g * tap.lowpass;
+            }
+            tap.delay = delay;
+            tap.gain = gain;
+            tap.coeff = coeff;
+        }
+        self.taps.retain(|_, tap| tap.gain > 0.0 || tap.target.1 > 0.0);
+    }
+}
+
+/// What the mixer plays for a unit impulse sent down `taps`, with no fade-in.
+pub fn impulse_response(taps: &[Tap]) -> Vec<f32> {
+    let mut state = VoiceState::new();
+    state.retarget(Some(&Voice { partials: Vec::new(), taps: taps.to_vec() }));
+    for tap in state.taps.values_mut() {
+        tap.gain = tap.target.1;
+    }
+    let longest = state.taps.values().map(|tap| tap.delay).fold(0.0, f32::max);
+    // Room for the tail of the slowest lowpass
+    let blocks = (longest as usize + SAMPLE_RATE as usize / 20) / BLOCK_SIZE + 1;
+
+    let len = state.history.len();
+    let mut response = vec![0.0; blocks * BLOCK_SIZE];
+    for (block, out) in response.chunks_mut(BLOCK_SIZE).enumerate() {
+        let start = (block * BLOCK_SIZE) as u64;
+        for i in 0..BLOCK_SIZE {
+            state.history[(start as usize + i) % len] = if start == 0 && i == 0 { 1.0 } else { 0.0 };
+        }
+        state.render_taps(start, BLOCK_SIZE);
+        for listener in state.heard.values() {
+            for (sample, heard) in out.iter_mut().zip(listener) {
+                *sample += heard;
+            }
+        }
+    }
+    response
+}
+
+fn read_history(history: &[f32], position: f64) -> f32 {
+    if position < 0.0 {
+        return 0.0;
+    }
+    let len = history.len();
+    let index = position.floor();
+    let frac = (position - index) as f32;
+    let a = history[index as usize % len];
+    let b = history[(index as usize + 1) % len];
+    a + (b - a) * frac
+}
+
+/// Renders every listener's mix block by block and plays the monitored one; owned by
+/// the audio thread.
+pub struct Mixer {
+    params: Arc<Mutex<Arc<MixParams>>>,
+    snapshot: Arc<MixParams>,
+    captures: Arc<Mutex<Captures>>,
+    voices: BTreeMap<Entity, (VoiceState, Vec<Partial>)>,
+    reverb: Option<PreparedReverb>,
+    // Only ever taken through `get_mut`, the lock just lets `OfflineAudio` hold a mixer
+    reverb_updates: Mutex<Receiver<Option<PreparedReverb>>>,
+    send: Vec<f32>,
+    // Every listener's mix, reused from block to block
+    outputs: BTreeMap<Listener, Vec<f32>>,
+    // Samples rendered while the ECS held the captures
+    dropped: usize,
+}
+
+impl Mixer {
+    pub fn render(&mut self, start: u64, out: &mut [f32]) {
+        out.fill(0.0);
+        // Never wait on the ECS, a contended lock just means mixing the last snapshot again
+        if let Ok(latest) = self.params.try_lock() {
+            if !Arc::ptr_eq(&latest, &self.snapshot) {
+                self.snapshot = latest.clone();
+            }
+        }
+        let params = self.snapshot.clone();
+
+        for (entity, (state, partials)) in self.voices.iter_mut() {
+            let voice = params.voices.get(entity);
+            state.retarget(voice);
+            if let Some(voice) = voice {
+                partials.clone_from(&voice.partials);
+            }
+        }
+        for (entity, voice) in &params.voices {
+            self.voices.entry(*entity).or_insert_with(|| {
+                let mut state = VoiceState::new();
+                state.retarget(Some(voice));
+                (state, voice.partials.clone())
+            });
+        }
+
+        self.send.resize(out.len(), 0.0);
+        self.send.fill(0.0);
+        for output in self.outputs.values_mut() {
+            output.fill(0.0);
+        }
+        for (state, partials) in self.voices.values_mut() {
+            state.synthesize(partials, start, &mut self.send);
+            state.render_taps(start, out.len());
+            for (listener, block) in &state.heard {
+                let output = self.outputs.entry(*listener).or_insert_with(|| vec![0.0; block.len()]);
+                for (sample, voice) in output.iter_mut().zip(block) {
+                    *sample += voice;
+                }
+            }
+        }
+        // The camera is always listening, microphones only while something reaches them
+        let voices = &self.voices;
+        self.outputs.retain(|listener, _| {
+            *listener == Listener::Camera || voices.values().any(|(state, _)| state.heard.contains_key(listener))
+        });
+        if let Some(monitored) = self.outputs.get(&params.monitor) {
+            out.copy_from_slice(monitored);
+        }
+
+        if let Some(reverb) = self.reverb_updates.get_mut().unwrap().try_iter().last() {
+            self.reverb = reverb;
+        }
+        if let Some((_, convolver)) = &mut self.reverb {
+            convolver.process(&self.send, out);
+        }
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+        self.capture(out, params.monitor);
+        self.voices
+            .retain(|entity, (state, _)| params.voices.contains_key(entity) || !state.taps.is_empty());
+    }
+
+    fn capture(&mut self, out: &[f32], monitor: Listener) {
+        // Never wait on the ECS copying a capture out either, the block is kept as silence
+        let Ok(mut captures) = self.captures.try_lock() else {
+            self.dropped += out.len();
+            return;
+        };
+        let limit = (RECORD_SECONDS * SAMPLE_RATE as f32) as usize;
+        let dropped = std::mem::take(&mut self.dropped);
+        captures.blocks += 1;
+        let block = captures.blocks;
+        let mut write = |signal: Signal, samples: &[f32]| {
+            let (written, capture) = captures
+                .signals
+                .entry(signal)
+                .or_insert_with(|| (block, VecDeque::with_capacity(limit + BLOCK_SIZE)));
+            *written = block;
+            capture.extend(std::iter::repeat_n(0.0, dropped.min(limit)));
+            capture.extend(samples);
+            let excess = capture.len().saturating_sub(limit);
+            capture.drain(..excess);
+        };
+        for (listener, output) in &self.outputs {
+            write(Signal::Listener(*listener), output);
+        }
+        for (entity, (state, _)) in &self.voices {
+            if let Some(heard) = state.heard.get(&monitor) {
+                write(Signal::Emitter(*entity), heard);
+            }
+        }
+        write(Signal::Master, out);
+        captures.signals.retain(|_, (written, _)| *written == block);
+        captures.rendered += (dropped + out.len()) as u64;
+    }
+}
+
+/// Mono rodio source pulling blocks from a `Mixer`.
+pub struct ListenerSource {
+    mixer: Mixer,
+    block: Vec<f32>,
+    cursor: usize,
+    rendered: u64,
+}
+
+impl ListenerSource {
+    pub fn new(mixer: Mixer) -> Self {
+        Self {
+            mixer,
+            block: vec![0.0; BLOCK_SIZE],
+            cursor: BLOCK_SIZE,
+            rendered: 0,
+        }
+    }
+}
+
+impl Iterator for ListenerSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.cursor == self.block.len() {
+            self.mixer.render(self.rendered, &mut self.block);
+            self.rendered += self.block.len() as u64;
+            self.cursor = 0;
+        }
+        let sample = self.block[self.cursor];
+        self.cursor += 1;
+        Some(sample)
+    }
+}
+
+impl Source for ListenerSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.block.len() - self.cursor)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
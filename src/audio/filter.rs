@@ -0,0 +1,62 @@
+use std::f32::consts::PI;
+
+/// Second-order IIR section in transposed direct form II, coefficients normalised by a0.
+pub struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    state: [f32; 2],
+}
+
+impl Biquad {
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Self {
+            b: b.map(|b| b / a[0]),
+            a: [a[1] / a[0], a[2] / a[0]],
+            state: [0.0; 2],
+        }
+    }
+
+    // RBJ band-pass with constant 0 dB peak gain, one octave wide
+    pub fn band_pass(center: f32, sample_rate: f32) -> Self {
+        let w0 = 2.0 * PI * center.min(sample_rate * 0.45) / sample_rate;
+        let alpha = w0.sin() * (2f32.ln() / 2.0 * w0 / w0.sin()).sinh();
+        Self::normalized([alpha, 0.0, -alpha], [1.0 + alpha, -2.0 * w0.cos(), 1.0 - alpha])
+    }
+
+    /// RBJ high shelf with `gain` dB above `corner`.
+    pub fn high_shelf(corner: f32, gain: f32, q: f32, sample_rate: f32) -> Self {
+        let a = 10f32.powf(gain / 40.0);
+        let w0 = 2.0 * PI * corner / sample_rate;
+        let (cos, alpha) = (w0.cos(), w0.sin() / (2.0 * q));
+        let root = 2.0 * a.sqrt() * alpha;
+        Self::normalized(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos + root),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - root),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos + root,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - root,
+            ],
+        )
+    }
+
+    /// RBJ high-pass.
+    pub fn high_pass(corner: f32, q: f32, sample_rate: f32) -> Self {
+        let w0 = 2.0 * PI * corner / sample_rate;
+        let (cos, alpha) = (w0.cos(), w0.sin() / (2.0 * q));
+        Self::normalized(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use crate::audio::engine::{AudioEngine, Listener};
+use crate::audio::spectrum::{peaks, Analyzer, ANALYSIS_SIZE};
+use crate::extras::components::AudioEmitter;
+use crate::music::chord::{recognize, Harmony, DEFAULT_TOLERANCE};
+
+// Peaks this far below the loudest are left out, Hann sidelobes sit around -31 dB
+const PEAK_RANGE: f32 = 24.0; // in dB
+const MAX_PEAKS: usize = 6;
+const MAX_HARMONIC: f32 = 8.0;
+
+/// What the emitters are tuned to and what the camera actually hears, as named harmony
+/// with the largest deviation in cents.
+#[derive(Resource, Default)]
+pub struct HarmonyAnalysis {
+    pub emitters: Option<(Harmony, f32)>,
+    pub heard: Option<(Harmony, f32)>,
+    pub peaks: Vec<f32>, // in Hz, lowest first
+}
+
+/// Keeps the strongest peaks that are not overtones of a lower kept one.
+fn fundamentals(mut found: Vec<(f32, f32)>) -> Vec<f32> {
+    found.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut kept: Vec<f32> = Vec::new();
+    for (frequency, _) in found {
+        let is_overtone = kept.iter().any(|lower| {
+            let ratio = frequency / lower;
+            let harmonic = ratio.round();
+            (2.0..=MAX_HARMONIC).contains(&harmonic) && 1200.0 * (ratio / harmonic).log2().abs() < DEFAULT_TOLERANCE
+        });
+        if !is_overtone {
+            kept.push(frequency);
+        }
+    }
+    kept
+}
+
+pub fn recognize_harmony(
+    engine: Res<AudioEngine>,
+    emitter_query: Query<&AudioEmitter>,
+    mut analysis: ResMut<HarmonyAnalysis>,
+    mut analyzer: Local<Analyzer>,
+) {
+    let frequencies: Vec<f32> = emitter_query.iter().map(|emitter| emitter.frequency).collect();
+    let emitters = recognize(&frequencies, DEFAULT_TOLERANCE);
+    if emitters.map(|(harmony, _)| harmony) != analysis.emitters.map(|(harmony, _)| harmony) {
+        match emitters {
+            Some((harmony, _)) => info!("Emitters form {harmony}"),
+            None => info!("Emitters form no recognised chord"),
+        }
+    }
+    analysis.emitters = emitters;
+
+    let samples = engine.recent(Listener::Camera, ANALYSIS_SIZE).unwrap_or_default();
+    analysis.peaks = fundamentals(peaks(&analyzer.magnitudes(&samples), PEAK_RANGE, MAX_PEAKS));
+    analysis.heard = recognize(&analysis.peaks, DEFAULT_TOLERANCE);
+}
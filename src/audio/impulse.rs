@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use std::path::{Path, PathBuf};
+use crate::acoustics::propagation::PropagationPaths;
+use crate::acoustics::raytrace::ReverbEstimate;
+use crate::audio::engine::{impulse_response, Listener, SAMPLE_RATE};
+use crate::extras::resources::SelectionState;
+
+// Longer files are cut, the convolver's cost grows with every second
+const MAX_LOADED_SECONDS: f32 = 10.0;
+
+/// An external impulse response the listener mix is convolved with.
+#[derive(Resource, Default)]
+pub struct LoadedImpulseResponse {
+    pub path: Option<PathBuf>,
+    pub samples: Option<Vec<f32>>,
+}
+
+/// Reads any PCM or float WAV as mono at `SAMPLE_RATE`.
+pub fn read_wav(path: &Path) -> Result<Vec<f32>, hound::Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = interleaved
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect();
+
+    // Linear resampling is plenty for a reverb tail
+    let step = spec.sample_rate as f64 / SAMPLE_RATE as f64;
+    let length = ((mono.len() as f64 / step) as usize).min((MAX_LOADED_SECONDS * SAMPLE_RATE as f32) as usize);
+    Ok((0..length)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let frac = (position - index as f64) as f32;
+            let a = mono[index];
+            let b = mono.get(index + 1).copied().unwrap_or(0.0);
+            a + (b - a) * frac
+        })
+        .collect())
+}
+
+pub fn write_wav(path: &Path, samples: &[f32]) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for sample in samples {
+        writer.write_sample(*sample)?;
+    }
+    writer.finalize()
+}
+
+/// Excites the selected emitter (or the first one) with an impulse through its current
+/// paths to the listener, adds the traced late reverb, and saves the result.
+pub fn export_impulse_response(
+    selection_state: Res<SelectionState>,
+    paths: Res<PropagationPaths>,
+    estimate: Res<ReverbEstimate>,
+) {
+    let chosen = paths
+        .emitters
+        .iter()
+        .find(|(entity, _)| Some(*entity) == selection_state.selected_entity)
+        .or(paths.emitters.first());
+    let Some((entity, emitter_paths)) = chosen else {
+        warn!("No emitter to measure an impulse response from");
+        return;
+    };
+
+    let taps: Vec<_> = emitter_paths.iter().map(|path| path.tap(Listener::Camera)).collect();
+    let mut response = impulse_response(&taps);
+    if let Some(late) = estimate.late_part() {
+        response.resize(response.len().max(late.len()), 0.0);
+        for (sample, tail) in response.iter_mut().zip(late) {
+            *sample += tail;
+        }
+    }
+
+    let path = PathBuf::from(format!("impulse_response_{}.wav", entity.index()));
+    match write_wav(&path, &response) {
+        Ok(()) => info!(
+            "Saved {:.2} s impulse response of {entity:?} to {}",
+            response.len() as f32 / SAMPLE_RATE as f32,
+            path.display(),
+        ),
+        Err(err) => warn!("Could not write {}: {err}", path.display()),
+    }
+}
+
+/// Dropping a WAV on the window loads it as the convolution reverb.
+pub fn load_dropped_impulse_response(
+    mut drops: EventReader<FileDragAndDrop>,
+    mut loaded: ResMut<LoadedImpulseResponse>,
+) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else { continue };
+        if !path_buf.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("wav")) {
+            continue;
+        }
+        match read_wav(path_buf) {
+            Ok(samples) => {
+                info!(
+                    "Convolving with {} ({:.2} s)",
+                    path_buf.display(),
+                    samples.len() as f32 / SAMPLE_RATE as f32,
+                );
+                loaded.path = Some(path_buf.clone());
+                loaded.samples = Some(samples);
+            }
+            Err(err) => warn!("Could not read {}: {err}", path_buf.display()),
+        }
+    }
+}
+
+pub fn unload_impulse_response(mut loaded: ResMut<LoadedImpulseResponse>) {
+    if let Some(path) = loaded.path.take() {
+        info!("Stopped convolving with {}", path.display());
+    }
+    loaded.samples = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_wav(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{name}-{}.wav", std::process::id()))
+    }
+
+    #[test]
+    fn written_responses_read_back_unchanged() {
+        let path = temp_wav("response");
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin() * 0.9).collect();
+        write_wav(&path, &samples).unwrap();
+        let read = read_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn other_files_are_mixed_down_and_resampled() {
+        let path = temp_wav("stereo");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: SAMPLE_RATE / 2,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        // Left and right average to a ramp of 0, 0.25, 0.5
+        for (left, right) in [(0, 0), (16384, 0), (16384, 16384)] {
+            writer.write_sample(left as i16).unwrap();
+            writer.write_sample(right as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        let read = read_wav(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read, [0.0, 0.125, 0.25, 0.375, 0.5, 0.25]);
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::PI;
+use crate::audio::engine::{AudioEngine, Signal, SAMPLE_RATE};
+use crate::audio::filter::Biquad;
+use crate::audio::spectrum::{to_db, FLOOR_DB};
+use crate::music::tuning::TunedNote;
+use crate::extras::components::AudioEmitter;
+
+// Loudness is gathered in 100 ms steps, BS.1770 blocks overlap by 75%
+const STEP: usize = SAMPLE_RATE as usize / 10;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+// The RMS readout follows a 300 ms window like a classic VU
+const RMS_STEPS: usize = 3;
+const ABSOLUTE_GATE: f32 = -70.0; // in LUFS
+const RELATIVE_GATE: f32 = -10.0; // in LU below the absolute-gated loudness
+// True peak is looked for between samples at four times the rate
+const OVERSAMPLING: usize = 4;
+const TRUE_PEAK_TAPS: usize = 48;
+
+fn lufs(mean_square: f32) -> f32 {
+    (-0.691 + 10.0 * mean_square.max(1e-12).log10()).max(FLOOR_DB)
+}
+
+/// Gated mean of 400 ms block powers, as in BS.1770-4.
+fn integrated(blocks: &[f32]) -> Option<f32> {
+    let gated = |threshold: f32| {
+        let kept: Vec<f32> = blocks.iter().copied().filter(|block| lufs(*block) > threshold).collect();
+        (!kept.is_empty()).then(|| kept.iter().sum::<f32>() / kept.len() as f32)
+    };
+    let absolute = gated(ABSOLUTE_GATE)?;
+    gated(lufs(absolute) + RELATIVE_GATE).map(lufs)
+}
+
+/// Polyphase windowed-sinc interpolator, one filter per position between samples.
+fn oversampling_phases() -> Vec<Vec<f32>> {
+    let center = (TRUE_PEAK_TAPS - 1) as f32 / 2.0;
+    let taps: Vec<f32> = (0..TRUE_PEAK_TAPS)
+        .map(|n| {
+            let x = (n as f32 - center) / OVERSAMPLING as f32;
+            let sinc = if x.abs() < 1e-6 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let window = 0.5 - 0.5 * (2.0 * PI * n as f32 / (TRUE_PEAK_TAPS - 1) as f32).cos();
+            sinc * window
+        })
+        .collect();
+    (0..OVERSAMPLING).map(|phase| taps.iter().skip(phase).step_by(OVERSAMPLING).copied().collect()).collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LoudnessReading {
+    pub momentary: f32,  // in LUFS
+    pub short_term: f32, // in LUFS
+    pub integrated: Option<f32>, // in LUFS, since the last reset
+    pub true_peak: f32,  // held since the last reset, in dBTP
+    pub rms: f32,        // in dBFS
+}
+
+impl Default for LoudnessReading {
+    fn default() -> Self {
+        Self {
+            momentary: FLOOR_DB,
+            short_term: FLOOR_DB,
+            integrated: None,
+            true_peak: FLOOR_DB,
+            rms: FLOOR_DB,
+        }
+    }
+}
+
+/// K-weighted loudness, true peak and RMS of one signal.
+pub struct LoudnessMeter {
+    position: Option<u64>, // stream sample the next call continues from
+    shelf: Biquad,
+    high_pass: Biquad,
+    step: (f32, f32, usize), // weighted and plain sums of squares, samples
+    steps: VecDeque<(f32, f32)>,
+    blocks: Vec<f32>,
+    phases: Vec<Vec<f32>>,
+    recent: VecDeque<f32>,
+    true_peak: f32,
+}
+
+impl Default for LoudnessMeter {
+    fn default() -> Self {
+        let sample_rate = SAMPLE_RATE as f32;
+        Self {
+            position: None,
+            // The two stages of the K-weighting curve
+            shelf: Biquad::high_shelf(1500.0, 4.0, 1.0 / 2f32.sqrt(), sample_rate),
+            high_pass: Biquad::high_pass(38.0, 0.5, sample_rate),
+            step: (0.0, 0.0, 0),
+            steps: VecDeque::new(),
+            blocks: Vec::new(),
+            phases: oversampling_phases(),
+            recent: VecDeque::from(vec![0.0; TRUE_PEAK_TAPS / OVERSAMPLING]),
+            true_peak: 0.0,
+        }
+    }
+}
+
+impl LoudnessMeter {
+    /// Starts the integrated loudness and true peak over.
+    pub fn reset(&mut self) {
+        self.blocks.clear();
+        self.true_peak = 0.0;
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            let weighted = self.high_pass.process(self.shelf.process(sample));
+            self.step.0 += weighted * weighted;
+            self.step.1 += sample * sample;
+            self.step.2 += 1;
+            if self.step.2 == STEP {
+                self.steps.push_back((self.step.0 / STEP as f32, self.step.1 / STEP as f32));
+                if self.steps.len() > SHORT_TERM_STEPS {
+                    self.steps.pop_front();
+                }
+                self.step = (0.0, 0.0, 0);
+                if self.steps.len() >= MOMENTARY_STEPS {
+                    self.blocks.push(self.mean(MOMENTARY_STEPS).0);
+                }
+            }
+
+            self.recent.pop_front();
+            self.recent.push_back(sample);
+            for phase in &self.phases {
+                let between: f32 = phase.iter().rev().zip(&self.recent).map(|(tap, x)| tap * x).sum();
+                self.true_peak = self.true_peak.max(between.abs());
+            }
+            self.true_peak = self.true_peak.max(sample.abs());
+        }
+    }
+
+    /// Mean weighted and plain power of the last `count` steps.
+    fn mean(&self, count: usize) -> (f32, f32) {
+        let recent = self.steps.iter().rev().take(count);
+        let len = recent.len().max(1) as f32;
+        recent.fold((0.0, 0.0), |sum, step| (sum.0 + step.0 / len, sum.1 + step.1 / len))
+    }
+
+    pub fn reading(&self) -> LoudnessReading {
+        LoudnessReading {
+            momentary: lufs(self.mean(MOMENTARY_STEPS).0),
+            short_term: lufs(self.mean(SHORT_TERM_STEPS).0),
+            integrated: integrated(&self.blocks),
+            true_peak: to_db(self.true_peak),
+            rms: to_db(self.mean(RMS_STEPS).1.sqrt()),
+        }
+    }
+}
+
+/// BS.1770 loudness of what reaches the speakers and of each emitter's share of the
+/// monitored listener.
+#[derive(Resource, Default)]
+pub struct Loudness {
+    pub master: LoudnessReading,
+    pub emitters: Vec<(Entity, String, LoudnessReading)>,
+    meters: HashMap<Signal, LoudnessMeter>,
+}
+
+impl Loudness {
+    pub fn reset(&mut self) {
+        info!("Reset integrated loudness and true peak");
+        for meter in self.meters.values_mut() {
+            meter.reset();
+        }
+    }
+}
+
+pub fn update_loudness(
+    engine: Res<AudioEngine>,
+    emitter_query: Query<(Entity, Option<&TunedNote>), With<AudioEmitter>>,
+    mut loudness: ResMut<Loudness>,
+) {
+    let signals: Vec<Signal> = std::iter::once(Signal::Master)
+        .chain(emitter_query.iter().map(|(entity, _)| Signal::Emitter(entity)))
+        .collect();
+    loudness.meters.retain(|signal, _| signals.contains(signal));
+    for signal in signals {
+        let meter = loudness.meters.entry(signal).or_default();
+        // Nothing before the first call is measured
+        let Some((samples, end)) = engine.since(signal, meter.position.unwrap_or(u64::MAX)) else { continue };
+        meter.process(&samples);
+        meter.position = Some(end);
+    }
+
+    loudness.master = loudness.meters.get(&Signal::Master).map(LoudnessMeter::reading).unwrap_or_default();
+    loudness.emitters = emitter_query
+        .iter()
+        .map(|(entity, note)| {
+            let name = note.map_or("Emitter".to_string(), |note| note.pitch.to_string());
+            let reading = loudness.meters.get(&Signal::Emitter(entity)).map(LoudnessMeter::reading);
+            (entity, name, reading.unwrap_or_default())
+        })
+        .collect();
+}
+
+pub fn reset_loudness(mut loudness: ResMut<Loudness>) {
+    loudness.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    fn sine(frequency: f32, amplitude: f32, phase: f32, seconds: usize) -> Vec<f32> {
+        let rate = SAMPLE_RATE as f32;
+        (0..seconds * SAMPLE_RATE as usize).map(|n| amplitude * (TAU * frequency * n as f32 / rate + phase).sin()).collect()
+    }
+
+    fn sample_peak(samples: &[f32]) -> f32 {
+        to_db(samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs())))
+    }
+
+    #[test]
+    fn tone_at_minus_20_dbfs_reads_minus_23_lufs() {
+        let samples = sine(1000.0, 0.1, 0.0, 3);
+        let mut meter = LoudnessMeter::default();
+        meter.process(&samples);
+        let reading = meter.reading();
+        let integrated = reading.integrated.unwrap();
+        assert!((integrated + 23.0).abs() < 0.2, "integrated {integrated} LUFS");
+        assert!((reading.momentary + 23.0).abs() < 0.2, "momentary {} LUFS", reading.momentary);
+        assert!(reading.true_peak >= sample_peak(&samples));
+    }
+
+    #[test]
+    fn true_peak_finds_the_peaks_between_samples() {
+        // A quarter of the sample rate, sampled 45 degrees off its peaks
+        let samples = sine(SAMPLE_RATE as f32 / 4.0, 0.5, TAU / 8.0, 1);
+        let mut meter = LoudnessMeter::default();
+        meter.process(&samples);
+        let sample_peak = sample_peak(&samples);
+        let true_peak = meter.reading().true_peak;
+        assert!(true_peak > sample_peak + 2.0, "true peak {true_peak} dBTP, sample peak {sample_peak} dBFS");
+    }
+
+    #[test]
+    fn silence_has_no_integrated_loudness() {
+        let mut meter = LoudnessMeter::default();
+        meter.process(&vec![0.0; SAMPLE_RATE as usize]);
+        assert!(meter.reading().integrated.is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+use crate::audio::engine::{AudioEngine, Listener, SAMPLE_RATE};
+use crate::audio::impulse::write_wav;
+use crate::audio::spectrum::{band_levels, to_db, Analyzer, ANALYSIS_SIZE, FLOOR_DB, SPECTRUM_BANDS};
+use crate::input::map::{Action, ActionState};
+
+// Roughly where a seated listener's ears are
+pub const EAR_HEIGHT: f32 = 1.2;
+// How fast the held peak falls back, in dB per second
+const PEAK_FALLBACK: f32 = 20.0;
+
+/// A point in the scene that hears the emitters like the camera does.
+#[derive(Component)]
+pub struct Microphone {
+    pub name: String,
+}
+
+/// Which listener feeds the speakers.
+#[derive(Resource, Default)]
+pub struct Monitor {
+    pub listener: Listener,
+}
+
+#[derive(Clone, Debug)]
+pub struct ListenerMeter {
+    pub listener: Listener,
+    pub name: String,
+    pub rms: f32,  // in dBFS
+    pub peak: f32, // held, in dBFS
+    pub spectrum: [f32; SPECTRUM_BANDS],
+}
+
+/// Levels and spectra of the camera and every microphone, camera first.
+#[derive(Resource, Default)]
+pub struct ListenerMeters {
+    pub meters: Vec<ListenerMeter>,
+}
+
+/// Selecting the ground with N held places a microphone there at ear height.
+pub fn place_microphone(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    window_query: Query<&Window>,
+    microphones: Query<&Microphone>,
+) {
+    if !keyboard.pressed(KeyCode::KeyN) || !actions.just_pressed(Action::Select) { return }
+    let Ok((camera, camera_transform)) = camera_query.get_single() else { return };
+    let Some(cursor_position) = window_query.single().cursor_position() else { return };
+    let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) else { return };
+    let Some(distance) = ray.intersect_plane(Vec3::ZERO, Plane3d::new(Vec3::Y)) else { return };
+
+    let position = ray.get_point(distance) + Vec3::Y * EAR_HEIGHT;
+    let name = format!("Mic {}", microphones.iter().count() + 1);
+    info!("Placed {name} at ({:.1}, {:.1})", position.x, position.z);
+    commands.spawn((
+        TransformBundle::from_transform(Transform::from_translation(position)),
+        Microphone { name },
+    ));
+}
+
+/// Removes the monitored microphone and goes back to the camera.
+pub fn remove_microphone(
+    mut commands: Commands,
+    mut monitor: ResMut<Monitor>,
+    microphones: Query<&Microphone>,
+) {
+    let Listener::Microphone(entity) = monitor.listener else { return };
+    if let Ok(microphone) = microphones.get(entity) {
+        info!("Removed {}", microphone.name);
+        commands.entity(entity).despawn_recursive();
+    }
+    monitor.listener = Listener::Camera;
+}
+
+pub fn sync_monitor(
+    monitor: Res<Monitor>,
+    microphones: Query<&Microphone>,
+    engine: Res<AudioEngine>,
+) {
+    if !monitor.is_changed() { return }
+    let name = match monitor.listener {
+        Listener::Camera => "camera",
+        Listener::Microphone(entity) => microphones.get(entity).map_or("microphone", |mic| mic.name.as_str()),
+    };
+    info!("Monitoring {name}");
+    engine.set_monitor(monitor.listener);
+}
+
+pub fn update_meters(
+    time: Res<Time>,
+    engine: Res<AudioEngine>,
+    microphones: Query<(Entity, &Microphone)>,
+    mut meters: ResMut<ListenerMeters>,
+    mut analyzer: Local<Analyzer>,
+) {
+    let listeners = std::iter::once((Listener::Camera, "Camera".to_string())).chain(
+        microphones
+            .iter()
+            .map(|(entity, microphone)| (Listener::Microphone(entity), microphone.name.clone())),
+    );
+    let previous = std::mem::take(&mut meters.meters);
+    for (listener, name) in listeners {
+        let samples = engine.recent(listener, ANALYSIS_SIZE).unwrap_or_default();
+        // Long enough to cover a frame, short enough to follow the signal
+        let window = &samples[samples.len().saturating_sub(SAMPLE_RATE as usize / 20)..];
+        let rms = (window.iter().map(|s| s * s).sum::<f32>() / window.len().max(1) as f32).sqrt();
+        let peak = to_db(window.iter().fold(0.0f32, |peak, s| peak.max(s.abs())));
+        let held = previous
+            .iter()
+            .find(|meter| meter.listener == listener)
+            .map_or(FLOOR_DB, |meter| meter.peak - PEAK_FALLBACK * time.delta_seconds());
+        meters.meters.push(ListenerMeter {
+            listener,
+            name,
+            rms: to_db(rms),
+            peak: peak.max(held),
+            spectrum: band_levels(&analyzer.magnitudes(&samples)),
+        });
+    }
+}
+
+/// Saves what every listener heard over the last few seconds, one WAV each.
+pub fn save_recordings(
+    engine: Res<AudioEngine>,
+    meters: Res<ListenerMeters>,
+) {
+    for meter in &meters.meters {
+        let Some(recording) = engine.recording(meter.listener) else { continue };
+        let path = PathBuf::from(format!("recording_{}.wav", meter.name.to_lowercase().replace(' ', "_")));
+        match write_wav(&path, &recording) {
+            Ok(()) => info!(
+                "Saved {:.1} s heard by {} to {}",
+                recording.len() as f32 / SAMPLE_RATE as f32,
+                meter.name,
+                path.display(),
+            ),
+            Err(err) => warn!("Could not write {}: {err}", path.display()),
+        }
+    }
+}
+
+pub fn draw_microphones(
+    monitor: Res<Monitor>,
+    meters: Res<ListenerMeters>,
+    microphones: Query<(Entity, &GlobalTransform), With<Microphone>>,
+    mut gizmos: Gizmos,
+) {
+    for (entity, transform) in microphones.iter() {
+        let position = transform.translation();
+        let listener = Listener::Microphone(entity);
+        let color = if monitor.listener == listener { Color::CYAN } else { Color::WHITE };
+        gizmos.sphere(position, Quat::IDENTITY, 0.15, color);
+        gizmos.line(position, Vec3::new(position.x, 0.0, position.z), Color::GRAY);
+        // A level bar next to the capsule, full height at 0 dBFS
+        if let Some(meter) = meters.meters.iter().find(|meter| meter.listener == listener) {
+            let level = (1.0 - meter.rms / FLOOR_DB).clamp(0.0, 1.0);
+            let base = position + Vec3::X * 0.3 - Vec3::Y * 0.5;
+            gizmos.line(base, base + Vec3::Y * level, Color::GREEN);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use crate::audio::engine::{AudioEngine, Mixer, BLOCK_SIZE};
+use crate::extras::resources::SimulationTime;
+
+/// Renders the mix on the main thread in step with the simulation instead of playing it,
+/// so captures, meters and analysis work without an audio device and come out the same
+/// on every run. While it exists no output is opened.
+#[derive(Resource)]
+pub struct OfflineAudio {
+    mixer: Option<Mixer>,
+    block: Vec<f32>,
+    rendered: u64,
+    recording: Option<Vec<f32>>, // everything the monitored listener heard, when kept
+}
+
+impl Default for OfflineAudio {
+    fn default() -> Self {
+        Self { mixer: None, block: vec![0.0; BLOCK_SIZE], rendered: 0, recording: None }
+    }
+}
+
+impl OfflineAudio {
+    /// Also keeps the whole mix, not just the last `RECORD_SECONDS` the captures hold.
+    pub fn recording() -> Self {
+        Self { recording: Some(Vec::new()), ..default() }
+    }
+
+    pub fn rendered(&self) -> u64 {
+        self.rendered
+    }
+
+    pub fn take_recording(&mut self) -> Option<Vec<f32>> {
+        self.recording.take()
+    }
+}
+
+/// Renders up to the sample the latest fixed step ended at; a step is a whole number of blocks.
+pub fn render_offline_audio(
+    engine: Res<AudioEngine>,
+    sim_time: Res<SimulationTime>,
+    mut offline: ResMut<OfflineAudio>,
+) {
+    let offline = &mut *offline;
+    let mixer = offline.mixer.get_or_insert_with(|| engine.mixer());
+    while offline.rendered < sim_time.audio_sample() {
+        mixer.render(offline.rendered, &mut offline.block);
+        offline.rendered += BLOCK_SIZE as u64;
+        if let Some(recording) = &mut offline.recording {
+            recording.extend_from_slice(&offline.block);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use crate::audio::engine::{AudioEngine, Listener, SAMPLE_RATE};
+use crate::music::note::Note;
+
+// Integration window of the difference function, about 21 ms
+const WINDOW: usize = 1024;
+const MIN_FREQUENCY: f32 = 60.0; // in Hz
+const MAX_FREQUENCY: f32 = 2000.0; // in Hz
+// Dips of the normalised difference below this count as periodic
+const THRESHOLD: f32 = 0.15;
+// Quieter than about -70 dBFS is treated as silence
+const SILENCE: f32 = 3e-4;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PitchEstimate {
+    pub frequency: f32, // in Hz
+    // 1 for a perfectly periodic signal, falling towards 0 for noise
+    pub clarity: f32,
+}
+
+/// Fundamental frequency of `samples` after de Cheveigné and Kawahara's YIN. Needs
+/// `WINDOW` samples plus one period of the lowest frequency of interest.
+pub fn yin(samples: &[f32], sample_rate: f32) -> Option<PitchEstimate> {
+    let min_lag = (sample_rate / MAX_FREQUENCY).floor() as usize;
+    let max_lag = ((sample_rate / MIN_FREQUENCY).ceil() as usize).min(samples.len().checked_sub(WINDOW)?);
+    if max_lag <= min_lag + 1 {
+        return None;
+    }
+
+    // Cumulative mean normalised difference, index 0 is 1 by definition
+    let mut normalized = vec![1.0; max_lag + 1];
+    let mut running = 0.0;
+    for lag in 1..=max_lag {
+        let difference: f32 = samples[..WINDOW]
+            .iter()
+            .zip(&samples[lag..lag + WINDOW])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running += difference;
+        normalized[lag] = if running > 0.0 { difference * lag as f32 / running } else { 1.0 };
+    }
+
+    // First dip under the threshold, followed down to its minimum
+    let mut lag = (min_lag..max_lag).find(|&lag| normalized[lag] < THRESHOLD)?;
+    while lag + 1 < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    // Parabolic interpolation between the neighbouring lags
+    let (a, b, c) = (normalized[lag - 1], normalized[lag], normalized[lag + 1]);
+    let curvature = a - 2.0 * b + c;
+    let shift = if curvature.abs() > f32::EPSILON { 0.5 * (a - c) / curvature } else { 0.0 };
+    Some(PitchEstimate {
+        frequency: sample_rate / (lag as f32 + shift.clamp(-1.0, 1.0)),
+        clarity: (1.0 - b).clamp(0.0, 1.0),
+    })
+}
+
+/// What the tuner currently hears at the camera.
+#[derive(Resource, Default)]
+pub struct DetectedPitch {
+    pub estimate: Option<PitchEstimate>,
+    pub note: Option<(Note, f32)>, // nearest note and cents off it
+}
+
+pub fn detect_listener_pitch(
+    engine: Res<AudioEngine>,
+    mut pitch: ResMut<DetectedPitch>,
+) {
+    let needed = WINDOW + (SAMPLE_RATE as f32 / MIN_FREQUENCY).ceil() as usize;
+    let samples = engine.recent(Listener::Camera, needed).unwrap_or_default();
+    let rms = (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt();
+    pitch.estimate = if rms > SILENCE { yin(&samples, SAMPLE_RATE as f32) } else { None };
+    pitch.note = pitch.estimate.map(|estimate| Note::nearest(estimate.frequency));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn yin_finds_a_sine_within_a_cent() {
+        let sample_rate = SAMPLE_RATE as f32;
+        let samples: Vec<f32> = (0..2 * WINDOW).map(|n| (TAU * 440.0 * n as f32 / sample_rate).sin()).collect();
+        let estimate = yin(&samples, sample_rate).unwrap();
+        let cents = 1200.0 * (estimate.frequency / 440.0).log2();
+        assert!(cents.abs() < 1.0, "{} Hz is {cents} cents off", estimate.frequency);
+        assert!(estimate.clarity > 0.9);
+    }
+
+    #[test]
+    fn yin_hears_no_pitch_in_silence() {
+        assert!(yin(&[0.0; 2 * WINDOW], SAMPLE_RATE as f32).is_none());
+    }
+}
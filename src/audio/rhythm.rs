@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use crate::audio::engine::{AudioEngine, SAMPLE_RATE};
+use crate::audio::microphone::Monitor;
+use crate::audio::spectrum::Analyzer;
+
+const FRAME: usize = 1024;
+const HOP: usize = 512; // about 94 detection frames per second
+// Spectral flux is averaged over this many frames to set the onset threshold
+const THRESHOLD_FRAMES: usize = 16;
+const THRESHOLD_RATIO: f32 = 1.5;
+// Keeps the noise floor of a steady tone from counting as onsets
+const MIN_FLUX: f32 = 1.0;
+const MIN_ONSET_GAP: f64 = 0.05; // in seconds
+// Detection function history the tempo is estimated from
+const TEMPO_SECONDS: f32 = 6.0;
+const MIN_TEMPO: f32 = 60.0; // in BPM
+const MAX_TEMPO: f32 = 180.0; // in BPM
+// Tempi are preferred around this, an octave away counts for about half
+const PREFERRED_TEMPO: f32 = 120.0; // in BPM
+const TEMPO_SPREAD: f32 = 1.4; // in octaves
+// Below this share of the signal's own energy the beat is considered lost
+const MIN_PERIODICITY: f32 = 0.3;
+// Onsets this close to a predicted beat, as a share of the period, pull the beat onto them
+const BEAT_WINDOW: f64 = 0.15;
+
+/// A sudden rise in spectral energy in the monitored mix.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct OnsetEvent {
+    pub time: f64, // in seconds of audio since the stream started
+    pub strength: f32,
+}
+
+/// A tick of the tracked pulse, predicted ahead from the estimated tempo.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BeatEvent {
+    pub time: f64, // in seconds of audio since the stream started
+    pub tempo: f32, // in BPM
+    pub index: u64,
+}
+
+/// Spectral flux onset detection and autocorrelation beat tracking over the monitored mix.
+#[derive(Resource)]
+pub struct RhythmTracker {
+    analyzer: Analyzer,
+    position: Option<u64>, // stream sample where `pending` starts
+    pending: Vec<f32>,
+    previous: Vec<f32>,
+    flux: VecDeque<f32>,
+    last_onset: f64,
+    pub tempo: Option<f32>, // in BPM
+    next_beat: Option<f64>,
+    beats: u64,
+}
+
+impl Default for RhythmTracker {
+    fn default() -> Self {
+        Self {
+            analyzer: Analyzer::new(FRAME),
+            position: None,
+            pending: Vec::new(),
+            previous: vec![0.0; FRAME / 2],
+            flux: VecDeque::new(),
+            last_onset: f64::NEG_INFINITY,
+            tempo: None,
+            next_beat: None,
+            beats: 0,
+        }
+    }
+}
+
+impl RhythmTracker {
+    fn frame_rate() -> f32 {
+        SAMPLE_RATE as f32 / HOP as f32
+    }
+
+    /// Rectified rise of the log-compressed spectrum since the previous frame.
+    fn spectral_flux(&mut self, frame: &[f32]) -> f32 {
+        let spectrum: Vec<f32> = self.analyzer.magnitudes(frame).iter().map(|m| (1.0 + 100.0 * m).ln()).collect();
+        let flux = spectrum.iter().zip(&self.previous).map(|(now, before)| (now - before).max(0.0)).sum();
+        self.previous = spectrum;
+        flux
+    }
+
+    /// Checks whether the frame before the newest one was a peak above the adaptive threshold.
+    fn onset(&self) -> Option<f32> {
+        let len = self.flux.len();
+        if len < 3 {
+            return None;
+        }
+        let (before, peak, after) = (self.flux[len - 3], self.flux[len - 2], self.flux[len - 1]);
+        let recent = self.flux.iter().rev().skip(2).take(THRESHOLD_FRAMES);
+        let mean = recent.clone().sum::<f32>() / recent.count().max(1) as f32;
+        (peak > before && peak >= after && peak > mean * THRESHOLD_RATIO + MIN_FLUX).then_some(peak)
+    }
+
+    /// Strongest periodicity of the detection function in the tempo range, weighted
+    /// towards `PREFERRED_TEMPO`. Without recent onsets there is nothing to follow.
+    fn estimate_tempo(&self, now: f64) -> Option<f32> {
+        if now - self.last_onset > TEMPO_SECONDS as f64 {
+            return None;
+        }
+        let mean = self.flux.iter().sum::<f32>() / self.flux.len().max(1) as f32;
+        let flux: Vec<f32> = self.flux.iter().map(|f| f - mean).collect();
+        let energy: f32 = flux.iter().map(|f| f * f).sum();
+        if energy <= 0.0 {
+            return None;
+        }
+        let lag_for = |tempo: f32| 60.0 * Self::frame_rate() / tempo;
+        let (shortest, longest) = (lag_for(MAX_TEMPO).floor() as usize, lag_for(MIN_TEMPO).ceil() as usize);
+        if flux.len() <= longest * 2 {
+            return None;
+        }
+        let (lag, periodicity, _) = (shortest..=longest)
+            .map(|lag| {
+                let correlation: f32 = flux.iter().zip(&flux[lag..]).map(|(a, b)| a * b).sum::<f32>()
+                    * flux.len() as f32
+                    / (flux.len() - lag) as f32
+                    / energy;
+                let octaves = (lag as f32 / lag_for(PREFERRED_TEMPO)).log2() / TEMPO_SPREAD;
+                (lag, correlation, correlation * (-0.5 * octaves * octaves).exp())
+            })
+            .max_by(|a, b| a.2.total_cmp(&b.2))?;
+        (periodicity >= MIN_PERIODICITY).then(|| 60.0 * Self::frame_rate() / lag as f32)
+    }
+}
+
+pub fn track_rhythm(
+    engine: Res<AudioEngine>,
+    monitor: Res<Monitor>,
+    mut tracker: ResMut<RhythmTracker>,
+    mut onsets: EventWriter<OnsetEvent>,
+    mut beats: EventWriter<BeatEvent>,
+) {
+    let wanted = tracker.position.map(|position| position + tracker.pending.len() as u64);
+    // Nothing before the first call is analysed
+    let Some((samples, end)) = engine.since(monitor.listener, wanted.unwrap_or(u64::MAX)) else { return };
+    let start = end - samples.len() as u64;
+    if wanted != Some(start) {
+        // First call, a new listener, or so far behind that the capture was trimmed
+        tracker.pending.clear();
+        tracker.position = Some(start);
+    }
+    tracker.pending.extend(samples);
+
+    let capacity = (TEMPO_SECONDS * RhythmTracker::frame_rate()) as usize;
+    let mut frame_start = tracker.position.unwrap();
+    while tracker.pending.len() >= FRAME {
+        let frame: Vec<f32> = tracker.pending[..FRAME].to_vec();
+        let flux = tracker.spectral_flux(&frame);
+        tracker.flux.push_back(flux);
+        if tracker.flux.len() > capacity {
+            tracker.flux.pop_front();
+        }
+        // The peak is the previous frame, centred half a frame into it
+        let time = (frame_start as f64 - HOP as f64 + FRAME as f64 / 2.0) / SAMPLE_RATE as f64;
+        if let Some(strength) = tracker.onset() {
+            if time - tracker.last_onset >= MIN_ONSET_GAP {
+                tracker.last_onset = time;
+                onsets.send(OnsetEvent { time, strength });
+                if let (Some(tempo), Some(next)) = (tracker.tempo, tracker.next_beat) {
+                    let period = 60.0 / tempo as f64;
+                    if (time - next).abs() < BEAT_WINDOW * period {
+                        tracker.next_beat = Some(next + 0.5 * (time - next));
+                    }
+                } else if let Some(tempo) = tracker.tempo {
+                    tracker.next_beat = Some(time + 60.0 / tempo as f64);
+                }
+            }
+        }
+        tracker.pending.drain(..HOP);
+        frame_start += HOP as u64;
+    }
+    tracker.position = Some(frame_start);
+
+    let now = end as f64 / SAMPLE_RATE as f64;
+    let tempo = tracker.estimate_tempo(now);
+    if tempo.map(f32::round) != tracker.tempo.map(f32::round) {
+        match tempo {
+            Some(tempo) => info!("Tempo {tempo:.0} BPM"),
+            None => info!("Lost the beat"),
+        }
+    }
+    tracker.tempo = tempo;
+    let Some(tempo) = tempo else {
+        tracker.next_beat = None;
+        return;
+    };
+    let period = 60.0 / tempo as f64;
+    while let Some(next) = tracker.next_beat.filter(|next| *next <= now) {
+        tracker.beats += 1;
+        beats.send(BeatEvent { time: next, tempo, index: tracker.beats });
+        tracker.next_beat = Some(next + period);
+    }
+}
@@ -0,0 +1,93 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+use crate::audio::engine::SAMPLE_RATE;
+
+pub const ANALYSIS_SIZE: usize = 4096;
+// Half-octave bands from 50 Hz up to about 12.8 kHz
+pub const SPECTRUM_BANDS: usize = 16;
+const LOWEST_BAND: f32 = 50.0;
+// Anything quieter reads as silence
+pub const FLOOR_DB: f32 = -90.0;
+
+/// Hann-windowed FFT of the latest `size` samples of a signal, `ANALYSIS_SIZE` by default.
+pub struct Analyzer {
+    size: usize,
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Self::new(ANALYSIS_SIZE)
+    }
+}
+
+/// Spacing of the bins when `bins` of them span up to Nyquist.
+pub fn bin_width(bins: usize) -> f32 {
+    SAMPLE_RATE as f32 / (2 * bins) as f32
+}
+
+impl Analyzer {
+    pub fn new(size: usize) -> Self {
+        let window: Vec<f32> = (0..size)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / size as f32).cos())
+            .collect();
+        Self {
+            size,
+            fft: FftPlanner::new().plan_fft_forward(size),
+            window,
+            scratch: vec![Complex::default(); size],
+        }
+    }
+
+    /// Amplitude of every bin up to Nyquist, scaled so a sine of amplitude 1 peaks near 1.
+    /// Short input is padded with silence at the start.
+    pub fn magnitudes(&mut self, samples: &[f32]) -> Vec<f32> {
+        let offset = self.size.saturating_sub(samples.len());
+        let samples = &samples[samples.len().saturating_sub(self.size)..];
+        self.scratch.fill(Complex::default());
+        for ((bin, sample), weight) in self.scratch[offset..].iter_mut().zip(samples).zip(&self.window[offset..]) {
+            bin.re = sample * weight;
+        }
+        self.fft.process(&mut self.scratch);
+        let scale = 2.0 / self.window.iter().sum::<f32>();
+        self.scratch[..self.size / 2].iter().map(|bin| bin.norm() * scale).collect()
+    }
+}
+
+pub fn to_db(amplitude: f32) -> f32 {
+    (20.0 * amplitude.max(1e-9).log10()).max(FLOOR_DB)
+}
+
+/// Loudest bin of each half-octave band, in dBFS.
+pub fn band_levels(magnitudes: &[f32]) -> [f32; SPECTRUM_BANDS] {
+    let width = bin_width(magnitudes.len());
+    std::array::from_fn(|band| {
+        let low = LOWEST_BAND * 2f32.powf(band as f32 / 2.0);
+        let high = low * std::f32::consts::SQRT_2;
+        let range = (low / width).ceil() as usize..(high / width).ceil() as usize;
+        to_db(magnitudes.get(range).map_or(0.0, |bins| bins.iter().copied().fold(0.0, f32::max)))
+    })
+}
+
+/// Local maxima of `magnitudes` within `range` dB of the loudest one and above the floor,
+/// strongest first, as (frequency, amplitude) with the frequency interpolated between bins.
+pub fn peaks(magnitudes: &[f32], range: f32, count: usize) -> Vec<(f32, f32)> {
+    let loudest = magnitudes.iter().copied().fold(0.0, f32::max);
+    let threshold = to_db(loudest) - range;
+    let mut found: Vec<(f32, f32)> = (1..magnitudes.len().saturating_sub(1))
+        .filter(|&bin| magnitudes[bin] > magnitudes[bin - 1] && magnitudes[bin] >= magnitudes[bin + 1])
+        .filter(|&bin| to_db(magnitudes[bin]) > threshold.max(FLOOR_DB))
+        .map(|bin| {
+            let (a, b, c) = (to_db(magnitudes[bin - 1]), to_db(magnitudes[bin]), to_db(magnitudes[bin + 1]));
+            let curvature = a - 2.0 * b + c;
+            let shift = if curvature.abs() > f32::EPSILON { 0.5 * (a - c) / curvature } else { 0.0 };
+            ((bin as f32 + shift) * bin_width(magnitudes.len()), magnitudes[bin])
+        })
+        .collect();
+    found.sort_by(|a, b| b.1.total_cmp(&a.1));
+    found.truncate(count);
+    found
+}
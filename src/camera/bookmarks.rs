@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
+use crate::camera::fly::{grab_cursor, CameraMode};
+use crate::camera::focus::{ease_in_out, CameraFocus};
+use crate::camera::orbit::{OrbitCamera, MAX_PITCH};
+use crate::extras::resources::SimulationTime;
+use crate::scene::layout::SceneLayout;
+
+// Digits 9 and 0 already set the air temperature
+const SLOT_KEYS: [KeyCode; 8] = [
+    KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8,
+];
+const RECALL_SECONDS: f32 = 1.0;
+// At the default speed multiplier this is five seconds of wall time between stops
+const DEFAULT_SEGMENT_SECONDS: f32 = 0.05; // of simulation time
+
+/// A named orbit pose recalled with its number key.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub slot: u8,
+    pub name: String,
+    pub focus: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub radius: f32,
+}
+
+impl Bookmark {
+    fn from_orbit(slot: u8, name: String, orbit: &OrbitCamera) -> Self {
+        Self { slot, name, focus: orbit.focus.to_array(), yaw: orbit.yaw, pitch: orbit.pitch, radius: orbit.radius }
+    }
+
+    fn orbit(&self) -> OrbitCamera {
+        OrbitCamera { focus: Vec3::from(self.focus), yaw: self.yaw, pitch: self.pitch, radius: self.radius }
+    }
+}
+
+/// A fly-through visiting bookmarks by name, timed in simulation seconds so a recording
+/// is the same on every run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FlyPath {
+    pub name: String,
+    pub stops: Vec<String>,
+    #[serde(default = "default_segment_seconds")]
+    pub segment_seconds: f32,
+}
+
+fn default_segment_seconds() -> f32 {
+    DEFAULT_SEGMENT_SECONDS
+}
+
+struct Tour {
+    poses: Vec<OrbitCamera>,
+    start: f32, // simulation time the tour started at
+    segment_seconds: f32,
+}
+
+/// What is moving the orbit on its own: easing to a recalled bookmark or a fly-through.
+#[derive(Resource, Default)]
+pub struct CameraPlayback {
+    recall: Option<(OrbitCamera, OrbitCamera, f32)>, // from, to and progress
+    tour: Option<Tour>,
+}
+
+/// The angle equal to `to` closest to `from`, so turns take the short way round.
+fn unwrap_angle(from: f32, to: f32) -> f32 {
+    from + (to - from + PI).rem_euclid(TAU) - PI
+}
+
+fn lerp_orbit(from: &OrbitCamera, to: &OrbitCamera, t: f32) -> OrbitCamera {
+    let yaw = unwrap_angle(from.yaw, to.yaw);
+    OrbitCamera {
+        focus: from.focus.lerp(to.focus, t),
+        yaw: from.yaw + (yaw - from.yaw) * t,
+        pitch: from.pitch + (to.pitch - from.pitch) * t,
+        radius: from.radius + (to.radius - from.radius) * t,
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+}
+
+impl Tour {
+    /// Pose `position` segments along a Catmull-Rom spline through every stop.
+    fn pose(&self, position: f32) -> OrbitCamera {
+        let last = self.poses.len() - 1;
+        let segment = (position.floor() as usize).min(last - 1);
+        let t = position - segment as f32;
+        let at = |i: isize| &self.poses[i.clamp(0, last as isize) as usize];
+        let [p0, p1, p2, p3] = [-1, 0, 1, 2].map(|offset| at(segment as isize + offset));
+        let angles = |pose: &OrbitCamera| Vec3::new(pose.yaw, pose.pitch, pose.radius);
+        let angles = catmull_rom(angles(p0), angles(p1), angles(p2), angles(p3), t);
+        OrbitCamera {
+            focus: catmull_rom(p0.focus, p1.focus, p2.focus, p3.focus, t),
+            yaw: angles.x,
+            pitch: angles.y.clamp(-MAX_PITCH, MAX_PITCH),
+            radius: angles.z.max(1.0),
+        }
+    }
+}
+
+/// A number key eases the orbit to its bookmark; with Ctrl held it stores the current pose
+/// there and writes the scene layout.
+pub fn bookmark_keys(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut layout: ResMut<SceneLayout>,
+    (mut mode, mut focus): (ResMut<CameraMode>, ResMut<CameraFocus>),
+    mut playback: ResMut<CameraPlayback>,
+    camera_query: Query<(&Transform, &OrbitCamera)>,
+    mut window_query: Query<&mut Window>,
+) {
+    let Some(slot) = SLOT_KEYS.iter().position(|key| keyboard.just_pressed(*key)).map(|index| index as u8 + 1) else {
+        return;
+    };
+    let Ok((transform, orbit)) = camera_query.get_single() else { return };
+    // In the fly camera the pose is whatever is straight ahead, as when switching back
+    let current = match *mode {
+        CameraMode::Orbit => *orbit,
+        CameraMode::Fly => OrbitCamera::looking_along(transform, orbit.radius),
+    };
+
+    if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        let existing = layout.bookmarks.iter().position(|bookmark| bookmark.slot == slot);
+        let name = existing.map_or(format!("Bookmark {slot}"), |index| layout.bookmarks[index].name.clone());
+        let bookmark = Bookmark::from_orbit(slot, name, &current);
+        match existing {
+            Some(index) => layout.bookmarks[index] = bookmark,
+            None => layout.bookmarks.push(bookmark),
+        }
+        layout.bookmarks.sort_by_key(|bookmark| bookmark.slot);
+        match layout.save() {
+            Ok(()) => info!("Saved bookmark {slot} to {}", layout.path.display()),
+            Err(err) => warn!("Could not write {}: {err}", layout.path.display()),
+        }
+        return;
+    }
+
+    let Some(bookmark) = layout.bookmarks.iter().find(|bookmark| bookmark.slot == slot) else {
+        info!("No bookmark {slot}, Ctrl+{slot} saves one");
+        return;
+    };
+    info!("Going to {}", bookmark.name);
+    playback.recall = Some((current, bookmark.orbit(), 0.0));
+    playback.tour = None;
+    focus.target = None;
+    if *mode == CameraMode::Fly {
+        *mode = CameraMode::Orbit;
+        if let Ok(mut window) = window_query.get_single_mut() {
+            grab_cursor(&mut window, false);
+        }
+    }
+}
+
+/// Starts the first path in the scene layout, or a tour of every bookmark if there is none,
+/// and stops it if already playing.
+pub fn toggle_tour(
+    layout: Res<SceneLayout>,
+    sim_time: Res<SimulationTime>,
+    mut mode: ResMut<CameraMode>,
+    mut focus: ResMut<CameraFocus>,
+    mut playback: ResMut<CameraPlayback>,
+    mut window_query: Query<&mut Window>,
+) {
+    if playback.tour.take().is_some() {
+        info!("Stopped the fly-through");
+        return;
+    }
+    let (name, stops, segment_seconds) = match layout.paths.first() {
+        Some(path) => {
+            let stops = path
+                .stops
+                .iter()
+                .filter_map(|stop| {
+                    let found = layout.bookmarks.iter().find(|bookmark| &bookmark.name == stop);
+                    if found.is_none() {
+                        warn!("Path {} stops at unknown bookmark {stop}", path.name);
+                    }
+                    found
+                })
+                .collect::<Vec<_>>();
+            (path.name.clone(), stops, path.segment_seconds)
+        }
+        None => ("every bookmark".to_string(), layout.bookmarks.iter().collect(), DEFAULT_SEGMENT_SECONDS),
+    };
+    if stops.len() < 2 {
+        info!("A fly-through needs at least two bookmarks");
+        return;
+    }
+
+    let mut poses: Vec<OrbitCamera> = stops.iter().map(|bookmark| bookmark.orbit()).collect();
+    for i in 1..poses.len() {
+        poses[i].yaw = unwrap_angle(poses[i - 1].yaw, poses[i].yaw);
+    }
+    info!("Flying through {name}, {} stops", poses.len());
+    playback.tour = Some(Tour { poses, start: sim_time.elapsed, segment_seconds: segment_seconds.max(f32::EPSILON) });
+    playback.recall = None;
+    focus.target = None;
+    if *mode == CameraMode::Fly {
+        *mode = CameraMode::Orbit;
+        if let Ok(mut window) = window_query.get_single_mut() {
+            grab_cursor(&mut window, false);
+        }
+    }
+}
+
+pub fn play_camera(
+    time: Res<Time>,
+    sim_time: Res<SimulationTime>,
+    mut playback: ResMut<CameraPlayback>,
+    mut orbit_query: Query<&mut OrbitCamera>,
+) {
+    let Ok(mut orbit) = orbit_query.get_single_mut() else { return };
+    if let Some(tour) = &playback.tour {
+        let position = (sim_time.elapsed - tour.start) / tour.segment_seconds;
+        *orbit = tour.pose(position.clamp(0.0, (tour.poses.len() - 1) as f32));
+        if position >= (tour.poses.len() - 1) as f32 {
+            info!("Fly-through finished");
+            playback.tour = None;
+        }
+    } else if let Some((from, to, progress)) = &mut playback.recall {
+        *progress = (*progress + time.delta_seconds() / RECALL_SECONDS).min(1.0);
+        *orbit = lerp_orbit(from, to, ease_in_out(*progress));
+        if *progress >= 1.0 {
+            playback.recall = None;
+        }
+    }
+}
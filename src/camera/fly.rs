@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseMotion;
+use bevy::window::CursorGrabMode;
+use crate::audio::microphone::EAR_HEIGHT;
+use crate::camera::focus::CameraFocus;
+use crate::camera::orbit::{OrbitCamera, MAX_PITCH};
+use crate::extras::components::Ground;
+use crate::extras::resources::CameraController;
+
+const WALK_SPEED: f32 = 2.0; // in m/s
+const RUN_FACTOR: f32 = 3.0;
+const CREEP_FACTOR: f32 = 0.3;
+const CLIMB_SPEED: f32 = 1.0; // in m/s
+// Eyes never get closer to the ground than this
+const MIN_EYE_HEIGHT: f32 = 0.2;
+// How quickly the eyes settle to the walking height, per second
+const SETTLE_RATE: f32 = 4.0;
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Orbit,
+    Fly,
+}
+
+/// First-person state, taken from wherever the camera was when fly mode started.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct FlyCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub height: f32, // eyes above the ground
+}
+
+impl Default for FlyCamera {
+    fn default() -> Self {
+        Self { yaw: 0.0, pitch: 0.0, height: EAR_HEIGHT }
+    }
+}
+
+/// Switches between the orbit rig and walking, keeping the camera exactly where it is.
+pub fn toggle_camera_mode(
+    mut mode: ResMut<CameraMode>,
+    mut focus: ResMut<CameraFocus>,
+    mut camera_query: Query<(&Transform, &mut OrbitCamera, &mut FlyCamera)>,
+    mut window_query: Query<&mut Window>,
+) {
+    let Ok((transform, mut orbit, mut fly)) = camera_query.get_single_mut() else { return };
+    *mode = match *mode {
+        CameraMode::Orbit => {
+            let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            *fly = FlyCamera { yaw, pitch: pitch.clamp(-MAX_PITCH, MAX_PITCH), height: EAR_HEIGHT };
+            focus.target = None;
+            CameraMode::Fly
+        }
+        CameraMode::Fly => {
+            // Orbit whatever is straight ahead at the distance the rig last had
+            *orbit = OrbitCamera::looking_along(transform, orbit.radius);
+            CameraMode::Orbit
+        }
+    };
+    info!("Camera mode {:?}", *mode);
+    if let Ok(mut window) = window_query.get_single_mut() {
+        grab_cursor(&mut window, *mode == CameraMode::Fly);
+    }
+}
+
+/// Locks and hides the cursor for mouse look, or gives it back.
+pub fn grab_cursor(window: &mut Window, grab: bool) {
+    window.cursor.grab_mode = if grab { CursorGrabMode::Locked } else { CursorGrabMode::None };
+    window.cursor.visible = !grab;
+}
+
+/// Mouse look, WASD to walk, Shift to run, Alt to creep, E and Q to raise and lower the eyes.
+pub fn fly_camera(
+    time: Res<Time>,
+    controller: Res<CameraController>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut ev_motion: EventReader<MouseMotion>,
+    ground_query: Query<&GlobalTransform, With<Ground>>,
+    mut camera_query: Query<(&mut Transform, &mut FlyCamera)>,
+) {
+    let Ok((mut transform, mut fly)) = camera_query.get_single_mut() else { return };
+    let dt = time.delta_seconds();
+    let motion: Vec2 = ev_motion.read().map(|ev| ev.delta).sum();
+    fly.yaw -= motion.x * controller.sensitivity;
+    fly.pitch = (fly.pitch - motion.y * controller.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    let rotation = Quat::from_euler(EulerRot::YXZ, fly.yaw, fly.pitch, 0.0);
+
+    // Walking stays level whatever the eyes are looking at
+    let forward = Quat::from_rotation_y(fly.yaw) * Vec3::NEG_Z;
+    let right = Quat::from_rotation_y(fly.yaw) * Vec3::X;
+    let mut direction = Vec3::ZERO;
+    for (key, step) in [(KeyCode::KeyW, forward), (KeyCode::KeyS, -forward), (KeyCode::KeyD, right), (KeyCode::KeyA, -right)] {
+        if keyboard.pressed(key) {
+            direction += step;
+        }
+    }
+    let mut speed = WALK_SPEED;
+    if keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        speed *= RUN_FACTOR;
+    }
+    // Not Ctrl, which saves bookmarks with the number keys
+    if keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        speed *= CREEP_FACTOR;
+    }
+    if keyboard.pressed(KeyCode::KeyE) {
+        fly.height += CLIMB_SPEED * dt;
+    }
+    if keyboard.pressed(KeyCode::KeyQ) {
+        fly.height -= CLIMB_SPEED * dt;
+    }
+    fly.height = fly.height.max(MIN_EYE_HEIGHT);
+
+    let ground = ground_query.get_single().map_or(0.0, |ground| ground.translation().y);
+    let mut position = transform.translation + direction.normalize_or_zero() * speed * dt;
+    // Settle to walking height from wherever the orbit left the camera
+    position.y += (ground + fly.height - position.y) * (1.0 - (-SETTLE_RATE * dt).exp());
+    position.y = position.y.max(ground + MIN_EYE_HEIGHT);
+
+    if position != transform.translation || rotation != transform.rotation {
+        *transform = Transform::from_translation(position).with_rotation(rotation);
+    }
+}
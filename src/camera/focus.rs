@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use crate::camera::orbit::OrbitCamera;
+use crate::extras::raycast::SphereBounds;
+use crate::extras::resources::SelectionState;
+use crate::input::map::{Action, ActionState};
+
+const TRANSITION_SECONDS: f32 = 0.8;
+// Close enough to fill about a quarter of the view with the object
+const FRAMING: f32 = 8.0; // in object radii
+const MIN_FRAMED_RADIUS: f32 = 3.0;
+
+/// Where the orbit is heading and whether it keeps following the target once there.
+#[derive(Resource)]
+pub struct CameraFocus {
+    pub target: Option<Entity>,
+    pub tracking: bool,
+    from: (Vec3, f32), // focus and radius the transition started at
+    to_radius: f32,
+    progress: f32, // 0 to 1 through the transition
+}
+
+impl Default for CameraFocus {
+    fn default() -> Self {
+        Self {
+            target: None,
+            tracking: true,
+            from: (Vec3::ZERO, 0.0),
+            to_radius: 0.0,
+            progress: 1.0,
+        }
+    }
+}
+
+pub fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+}
+
+/// Starts a transition when a new object is selected or F is pressed with one selected.
+pub fn focus_on_selection(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    selection_state: Res<SelectionState>,
+    mut previous: Local<Option<Entity>>,
+    bounds_query: Query<(&Transform, Option<&SphereBounds>)>,
+    orbit_query: Query<&OrbitCamera>,
+    mut focus: ResMut<CameraFocus>,
+) {
+    let selected = selection_state.selected_entity;
+    let newly_selected = selected != *previous;
+    *previous = selected;
+    if selected.is_none() && newly_selected {
+        focus.target = None;
+        return;
+    }
+    if !newly_selected && !keyboard.just_pressed(KeyCode::KeyF) { return }
+    let (Some(entity), Ok(orbit)) = (selected, orbit_query.get_single()) else { return };
+    let Ok((transform, bounds)) = bounds_query.get(entity) else { return };
+
+    let size = bounds.map_or(0.5, |bounds| bounds.radius) * transform.scale.max_element();
+    focus.target = Some(entity);
+    focus.from = (orbit.focus, orbit.radius);
+    focus.to_radius = (size * FRAMING).max(MIN_FRAMED_RADIUS);
+    focus.progress = 0.0;
+}
+
+pub fn toggle_focus_tracking(mut focus: ResMut<CameraFocus>) {
+    focus.tracking = !focus.tracking;
+    info!("Camera tracking {}", if focus.tracking { "on" } else { "off" });
+}
+
+/// Eases the orbit towards the target, then follows it while tracking. Panning by hand
+/// lets go of the target.
+pub fn follow_focus(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    // Selectable objects are top level, and their own transforms are current this frame
+    transforms: Query<&Transform>,
+    mut orbit_query: Query<&mut OrbitCamera>,
+    mut focus: ResMut<CameraFocus>,
+) {
+    let Some(target) = focus.target else { return };
+    let Ok(mut orbit) = orbit_query.get_single_mut() else { return };
+    let Ok(transform) = transforms.get(target) else {
+        focus.target = None;
+        return;
+    };
+    if actions.pressed(Action::Pan) || actions.stick(Action::Pan) != Vec2::ZERO {
+        focus.target = None;
+        return;
+    }
+
+    let position = transform.translation;
+    if focus.progress < 1.0 {
+        focus.progress = (focus.progress + time.delta_seconds() / TRANSITION_SECONDS).min(1.0);
+        let t = ease_in_out(focus.progress);
+        // Aim at where the target is now, so a moving one is met rather than chased
+        orbit.focus = focus.from.0.lerp(position, t);
+        orbit.radius = focus.from.1 + (focus.to_radius - focus.from.1) * t;
+    } else if focus.tracking && orbit.focus != position {
+        orbit.focus = position;
+    } else if !focus.tracking {
+        focus.target = None;
+    }
+}
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseMotion;
+use crate::camera::fly::grab_cursor;
+use crate::extras::resources::CameraController;
+use crate::input::map::{Action, ActionState};
+
+const MIN_RADIUS: f32 = 1.0;
+const MAX_RADIUS: f32 = 50.0;
+// Just short of straight up or down, where yaw stops meaning anything
+pub const MAX_PITCH: f32 = 1.5;
+// Holding a zoom key zooms like this many scroll lines a second
+const HELD_ZOOM_RATE: f32 = 5.0;
+const STICK_DRAG_RATE: f32 = 400.0; // in pixels per second
+
+/// Orbit, pan and zoom rig. The camera transform is derived from this state, never the
+/// other way round, so nothing drifts between frames.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub focus: Vec3,
+    pub yaw: f32,   // around +Y, 0 looks down -Z
+    pub pitch: f32, // above the focus, in radians
+    pub radius: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self::looking_from(Vec3::new(0.0, 5.0, 10.0), Vec3::ZERO)
+    }
+}
+
+impl OrbitCamera {
+    /// The rig that puts the camera at `position` looking at `focus`.
+    pub fn looking_from(position: Vec3, focus: Vec3) -> Self {
+        let offset = position - focus;
+        let radius = offset.length().clamp(MIN_RADIUS, MAX_RADIUS);
+        Self {
+            focus,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / offset.length().max(f32::EPSILON)).clamp(-1.0, 1.0).asin().clamp(-MAX_PITCH, MAX_PITCH),
+            radius,
+        }
+    }
+
+    /// The rig orbiting whatever is `radius` straight ahead of `transform`.
+    pub fn looking_along(transform: &Transform, radius: f32) -> Self {
+        Self::looking_from(transform.translation, transform.translation + *transform.forward() * radius)
+    }
+
+    pub fn transform(&self) -> Transform {
+        let offset = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        ) * self.radius;
+        Transform::from_translation(self.focus + offset).looking_at(self.focus, Vec3::Y)
+    }
+}
+
+/// Dragging with the orbit or pan action held moves the rig, as do the gamepad sticks;
+/// the zoom actions move it in and out.
+pub fn orbit_camera(
+    time: Res<Time>,
+    controller: Res<CameraController>,
+    actions: Res<ActionState>,
+    mut ev_motion: EventReader<MouseMotion>,
+    mut query: Query<(&mut Transform, &mut OrbitCamera)>,
+    mut window_query: Query<&mut Window>,
+) {
+    let dt = time.delta_seconds();
+    let motion: Vec2 = ev_motion.read().map(|ev| ev.delta).sum();
+    let mut zoom = actions.scrolled(Action::ZoomIn) - actions.scrolled(Action::ZoomOut);
+    if actions.pressed(Action::ZoomIn) {
+        zoom += HELD_ZOOM_RATE * dt;
+    }
+    if actions.pressed(Action::ZoomOut) {
+        zoom -= HELD_ZOOM_RATE * dt;
+    }
+    // Sticks count as this many pixels of drag a second at full deflection
+    let orbit_drag = if actions.pressed(Action::Orbit) { motion } else { Vec2::ZERO }
+        + actions.stick(Action::Orbit) * Vec2::new(1.0, -1.0) * STICK_DRAG_RATE * dt;
+    let pan_drag = if actions.pressed(Action::Pan) { motion } else { Vec2::ZERO }
+        + actions.stick(Action::Pan) * Vec2::new(1.0, -1.0) * STICK_DRAG_RATE * dt;
+
+    if let Ok(mut window) = window_query.get_single_mut() {
+        if actions.just_pressed(Action::Orbit) {
+            grab_cursor(&mut window, true);
+        }
+        if actions.just_released(Action::Orbit) {
+            grab_cursor(&mut window, false);
+        }
+    }
+
+    for (mut transform, mut orbit) in query.iter_mut() {
+        if orbit_drag != Vec2::ZERO {
+            orbit.yaw -= orbit_drag.x * controller.sensitivity;
+            orbit.pitch = (orbit.pitch + orbit_drag.y * controller.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        if pan_drag != Vec2::ZERO {
+            // Drag the scene along with the cursor, faster the further out the camera is
+            let (right, up) = (*transform.right(), *transform.up());
+            let scale = controller.pan_sensitivity * orbit.radius;
+            orbit.focus += (-right * pan_drag.x + up * pan_drag.y) * scale;
+        }
+        if zoom != 0.0 {
+            orbit.radius = (orbit.radius * (1.0 - controller.zoom_speed).powf(zoom)).clamp(MIN_RADIUS, MAX_RADIUS);
+        }
+        if orbit.is_changed() {
+            *transform = orbit.transform();
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use crate::extras::components::Selectable;
+use crate::extras::raycast::{closest_sphere, SphereBounds};
+use crate::extras::resources::SelectionState;
+use crate::input::map::{Action, ActionState};
+
+/// Selects the closest `Selectable` under the cursor, or clears the selection on a miss.
+pub fn object_selection(
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    window_query: Query<&Window>,
+    actions: Res<ActionState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut selection_state: ResMut<SelectionState>,
+    selectable_query: Query<(Entity, &GlobalTransform, &SphereBounds), With<Selectable>>,
+) {
+    // With N held the click places a microphone instead
+    if !actions.just_pressed(Action::Select) || keyboard.pressed(KeyCode::KeyN) { return }
+    let (camera, camera_transform) = camera_query.single();
+    let window = window_query.single();
+
+    if let Some(cursor_position) = window.cursor_position() {
+        if let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) {
+            let closest_hit = closest_sphere(ray.origin, *ray.direction, selectable_query.iter());
+            selection_state.selected_entity = closest_hit.map(|(entity, _)| entity);
+        }
+    }
+}
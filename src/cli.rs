@@ -0,0 +1,210 @@
+use std::path::PathBuf;
+use fundamentals::audio::engine::SAMPLE_RATE;
+use fundamentals::resources::MAX_SPEED_MULTIPLIER;
+use fundamentals::simulation::TICK_SAMPLES;
+
+pub const USAGE: &str = "\
+Usage: fundamentals [OPTIONS]
+
+Options:
+  --scene <FILE>            Room, emitters, walls, microphones and bookmarks to load and save [default: scene.toml]
+  --window <WIDTH>x<HEIGHT> Window size in logical pixels
+  --fullscreen              Start borderless fullscreen
+  --speed <MULTIPLIER>      Initial simulation speed, 0 to 0.1 [default: 0.01]
+  --audio-device <NAME>     Output device to play the mix on [default: the system's]
+  --sample-rate <HZ>        Output device sample rate [default: the device's]
+  --list-audio-devices      Print the output device names and exit
+  --headless                Run without a window, renderer or audio device
+  --ticks <N>               Length of a headless run in fixed steps of 1/60 s [default: 600]
+  --seconds <SECONDS>       Length of a headless run in seconds of audio
+  --render <FILE.wav>       Write the monitored mix of a headless run to a WAV file, implies --headless
+  -h, --help                Print this help";
+
+const DEFAULT_TICKS: u32 = 600;
+const MAX_WINDOW_SIZE: u32 = 16384;
+
+/// What the command line asked for, already checked.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub scene: Option<PathBuf>,
+    pub window: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub speed: Option<f32>,
+    pub audio_device: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub headless: Option<Headless>,
+}
+
+#[derive(Debug)]
+pub struct Headless {
+    pub ticks: u32,
+    pub render: Option<PathBuf>,
+}
+
+pub enum Command {
+    Run(Options),
+    Help,
+    ListAudioDevices,
+}
+
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{flag} needs a value"))
+}
+
+fn number<T: std::str::FromStr>(text: &str, flag: &str) -> Result<T, String> {
+    text.parse().map_err(|_| format!("{flag} expects a number, not {text}"))
+}
+
+fn window_size(text: &str) -> Result<(u32, u32), String> {
+    let (width, height) = text
+        .split_once('x')
+        .ok_or_else(|| format!("--window expects WIDTHxHEIGHT, like 1280x720, not {text}"))?;
+    let (width, height) = (number::<u32>(width, "--window")?, number::<u32>(height, "--window")?);
+    if !(1..=MAX_WINDOW_SIZE).contains(&width) || !(1..=MAX_WINDOW_SIZE).contains(&height) {
+        return Err(format!("--window sides have to be between 1 and {MAX_WINDOW_SIZE}"));
+    }
+    Ok((width, height))
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options::default();
+    let (mut headless, mut ticks, mut seconds, mut render) = (false, None, None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--list-audio-devices" => return Ok(Command::ListAudioDevices),
+            "--scene" => options.scene = Some(PathBuf::from(value(&mut args, &arg)?)),
+            "--window" => options.window = Some(window_size(&value(&mut args, &arg)?)?),
+            "--fullscreen" => options.fullscreen = true,
+            "--speed" => {
+                let speed: f32 = number(&value(&mut args, &arg)?, &arg)?;
+                if !(0.0..=MAX_SPEED_MULTIPLIER).contains(&speed) {
+                    return Err(format!("--speed has to be between 0 and {MAX_SPEED_MULTIPLIER}"));
+                }
+                options.speed = Some(speed);
+            }
+            "--audio-device" => options.audio_device = Some(value(&mut args, &arg)?),
+            "--sample-rate" => {
+                let rate: u32 = number(&value(&mut args, &arg)?, &arg)?;
+                if !(8_000..=384_000).contains(&rate) {
+                    return Err(format!("--sample-rate of {rate} Hz is not one a device would play"));
+                }
+                options.sample_rate = Some(rate);
+            }
+            "--headless" => headless = true,
+            "--ticks" => {
+                let count: u32 = number(&value(&mut args, &arg)?, &arg)?;
+                if count == 0 {
+                    return Err("--ticks has to be at least 1".to_string());
+                }
+                ticks = Some(count);
+            }
+            "--seconds" => {
+                let length: f32 = number(&value(&mut args, &arg)?, &arg)?;
+                if !(length > 0.0 && length.is_finite()) {
+                    return Err("--seconds has to be more than 0".to_string());
+                }
+                seconds = Some(length);
+            }
+            "--render" => {
+                let path = PathBuf::from(value(&mut args, &arg)?);
+                if !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("wav")) {
+                    return Err(format!("--render writes WAV files, {} does not end in .wav", path.display()));
+                }
+                render = Some(path);
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    if let Some(scene) = &options.scene {
+        // A new file is fine, the scene is written there once something is saved
+        if scene.is_dir() {
+            return Err(format!("--scene expects a file, {} is a directory", scene.display()));
+        }
+    }
+    let headless = headless || render.is_some();
+    if !headless && (ticks.is_some() || seconds.is_some()) {
+        return Err("--ticks and --seconds only apply to --headless or --render".to_string());
+    }
+    if headless {
+        if options.window.is_some() || options.fullscreen {
+            return Err("--window and --fullscreen need a window, which --headless does not open".to_string());
+        }
+        if options.audio_device.is_some() || options.sample_rate.is_some() {
+            return Err("--audio-device and --sample-rate pick a device, which --headless does not open".to_string());
+        }
+        let ticks = match (ticks, seconds) {
+            (Some(_), Some(_)) => return Err("give either --ticks or --seconds, not both".to_string()),
+            (Some(ticks), None) => ticks,
+            (None, Some(seconds)) => (seconds * SAMPLE_RATE as f32 / TICK_SAMPLES as f32).ceil() as u32,
+            (None, None) => DEFAULT_TICKS,
+        };
+        options.headless = Some(Headless { ticks, render });
+    }
+    Ok(Command::Run(options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Result<Options, String> {
+        match parse(args.iter().map(|arg| arg.to_string()))? {
+            Command::Run(options) => Ok(options),
+            Command::Help => Err("help".to_string()),
+            Command::ListAudioDevices => Err("list".to_string()),
+        }
+    }
+
+    #[test]
+    fn help_wins_over_anything_after_it() {
+        assert!(matches!(parse(["--help".to_string()].into_iter()), Ok(Command::Help)));
+        assert!(matches!(parse(["-h", "--bogus"].map(String::from).into_iter()), Ok(Command::Help)));
+        assert!(run(&[]).unwrap().headless.is_none());
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        assert_eq!(run(&["--bogus"]).unwrap_err(), "unknown option --bogus");
+        assert_eq!(run(&["scene.toml"]).unwrap_err(), "unknown option scene.toml");
+    }
+
+    #[test]
+    fn window_sizes_need_both_sides_in_range() {
+        assert_eq!(run(&["--window", "1280x720"]).unwrap().window, Some((1280, 720)));
+        assert_eq!(run(&["--window"]).unwrap_err(), "--window needs a value");
+        assert!(run(&["--window", "1280"]).unwrap_err().contains("WIDTHxHEIGHT"));
+        assert!(run(&["--window", "wide x720"]).unwrap_err().contains("expects a number"));
+        assert!(run(&["--window", "0x720"]).is_err());
+        assert!(run(&["--window", "1280x20000"]).is_err());
+    }
+
+    #[test]
+    fn speed_has_to_be_within_bounds() {
+        assert_eq!(run(&["--speed", "0.05"]).unwrap().speed, Some(0.05));
+        assert_eq!(run(&["--speed", "0"]).unwrap().speed, Some(0.0));
+        assert!(run(&["--speed", "-0.01"]).is_err());
+        assert!(run(&["--speed", &(MAX_SPEED_MULTIPLIER * 2.0).to_string()]).is_err());
+        assert!(run(&["--speed", "fast"]).is_err());
+    }
+
+    #[test]
+    fn headless_runs_count_ticks_or_seconds() {
+        let headless = |args: &[&str]| run(args).unwrap().headless.unwrap();
+        assert_eq!(headless(&["--headless"]).ticks, DEFAULT_TICKS);
+        assert_eq!(headless(&["--headless", "--ticks", "30"]).ticks, 30);
+        // One second is 48000 samples, 60 steps of 800
+        assert_eq!(headless(&["--headless", "--seconds", "1"]).ticks, 60);
+        assert_eq!(headless(&["--headless", "--seconds", "0.01"]).ticks, 1);
+        let render = headless(&["--render", "out.WAV"]);
+        assert_eq!(render.render, Some(PathBuf::from("out.WAV")));
+
+        assert!(run(&["--headless", "--ticks", "0"]).is_err());
+        assert!(run(&["--headless", "--ticks", "-3"]).is_err());
+        assert!(run(&["--headless", "--ticks", "30", "--seconds", "1"]).is_err());
+        assert!(run(&["--ticks", "30"]).is_err());
+        assert!(run(&["--headless", "--window", "800x600"]).is_err());
+        assert!(run(&["--render", "out.mp3"]).is_err());
+    }
+}
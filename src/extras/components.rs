@@ -17,19 +17,34 @@ pub struct Selectable;
 #[derive(Component)]
 pub struct ContentContainer;
 
+#[derive(Component)]
+pub struct Ground;
+
+/// One partial of an emitter's timbre relative to its fundamental. Ratios need not be
+/// whole, bells and bars have inharmonic spectra.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Harmonic {
+    pub ratio: f32,
+    pub amplitude: f32, // relative to the other partials
+    pub phase: f32,
+}
+
+impl Harmonic {
+    pub const FUNDAMENTAL: Self = Self { ratio: 1.0, amplitude: 1.0, phase: 0.0 };
+
+    pub fn nth(number: u32, amplitude: f32) -> Self {
+        Self { ratio: number as f32, amplitude, phase: 0.0 }
+    }
+}
+
 #[derive(Component)]
 pub struct AudioEmitter {
     pub frequency: f32, // in Hz
     pub amplitude: f32,
     pub phase: f32,
+    pub partials: Vec<Harmonic>,
 }
 
-#[derive(Component)]
-pub struct CameraOrbit {
-    pub radius: f32,
-    pub pitch: f32,
-    pub yaw: f32,
-}
 #[derive(Component)]
 pub struct ControlSection {
     pub section_type: SectionType,
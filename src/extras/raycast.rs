@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+/// Pickable and occluding extent of a sphere mesh, before its transform's scale.
+#[derive(Component)]
+pub struct SphereBounds {
+    pub radius: f32,
+}
+
+/// Distance along `direction` to the first hit on a sphere, if it is in front of `origin`.
+pub fn ray_sphere(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<f32> {
+    // Done in f64, the quadratic loses too much precision for far away spheres in f32
+    let offset_d = origin.as_dvec3() - center.as_dvec3();
+    let dir_vec: DVec3 = direction.as_dvec3();
+    let a = dir_vec.dot(dir_vec);
+    let b = 2.0 * dir_vec.dot(offset_d);
+    let c = offset_d.dot(offset_d) - (radius * radius) as f64;
+    let discr = b * b - 4.0 * a * c;
+    if discr < 0.0 {
+        return None;
+    }
+    let distance = ((-b - discr.sqrt()) / (2.0 * a)) as f32;
+    (distance >= 0.0).then_some(distance)
+}
+
+/// Closest `SphereBounds` entity hit by a ray.
+pub fn closest_sphere<'a>(
+    origin: Vec3,
+    direction: Vec3,
+    spheres: impl Iterator<Item = (Entity, &'a GlobalTransform, &'a SphereBounds)>,
+) -> Option<(Entity, f32)> {
+    let mut closest_hit: Option<(Entity, f32)> = None;
+    for (entity, transform, bounds) in spheres {
+        let (scale, _, center) = transform.to_scale_rotation_translation();
+        let Some(distance) = ray_sphere(origin, direction, center, bounds.radius * scale.max_element()) else {
+            continue;
+        };
+        match closest_hit {
+            None => closest_hit = Some((entity, distance)),
+            Some((_, best_dist)) if distance < best_dist => closest_hit = Some((entity, distance)),
+            _ => {}
+        }
+    }
+    closest_hit
+}
@@ -1,18 +1,49 @@
 use bevy::prelude::*;
+use crate::simulation::TICK_SAMPLES;
 
+// Fastest the speed control goes
+pub const MAX_SPEED_MULTIPLIER: f32 = 0.1;
+
+/// The simulation clock, advanced only in fixed steps of `TICK_SAMPLES` audio samples.
+/// `elapsed` is simulation seconds, scaled by the speed multiplier at every step.
 #[derive(Resource)]
 pub struct SimulationTime {
+    pub tick: u64,
     pub elapsed: f32,
     pub speed_multiplier: f32,
 }
 
-#[derive(Resource)]
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self { tick: 0, elapsed: 0.0, speed_multiplier: 0.01 }
+    }
+}
+
+impl SimulationTime {
+    /// Audio sample the current step ends at.
+    pub fn audio_sample(&self) -> u64 {
+        self.tick * TICK_SAMPLES as u64
+    }
+}
+
+#[derive(Resource, Clone, PartialEq)]
 pub struct CameraController {
-    pub sensitivity: f32,
-    pub zoom_speed: f32,
+    pub sensitivity: f32,     // orbit, in radians per pixel
+    pub zoom_speed: f32,      // share of the radius per scroll line
+    pub pan_sensitivity: f32, // share of the radius per pixel
 }
 
-#[derive(Resource)]
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            pan_sensitivity: 0.002,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
 pub struct SelectionState {
     pub selected_entity: Option<Entity>,
 }
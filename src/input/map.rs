@@ -0,0 +1,453 @@
+use bevy::prelude::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const USER_INPUT_FILE: &str = "input.toml";
+// Stick deflection below this is treated as resting
+const STICK_DEADZONE: f32 = 0.15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    Orbit,
+    Pan,
+    ZoomIn,
+    ZoomOut,
+    SpeedUp,
+    SpeedDown,
+    Pause,
+    Select,
+    Delete,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Orbit, Action::Pan, Action::ZoomIn, Action::ZoomOut, Action::SpeedUp,
+        Action::SpeedDown, Action::Pause, Action::Select, Action::Delete,
+    ];
+
+    /// Name used in the input file.
+    fn key(self) -> &'static str {
+        match self {
+            Action::Orbit => "orbit",
+            Action::Pan => "pan",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::SpeedUp => "speed_up",
+            Action::SpeedDown => "speed_down",
+            Action::Pause => "pause",
+            Action::Select => "select",
+            Action::Delete => "delete",
+        }
+    }
+
+    fn defaults(self) -> Vec<Binding> {
+        use Binding::*;
+        match self {
+            Action::Orbit => vec![Mouse(MouseButton::Right), Stick(Side::Right)],
+            Action::Pan => vec![Mouse(MouseButton::Middle), Stick(Side::Left)],
+            Action::ZoomIn => vec![Key(KeyCode::ArrowUp), ScrollUp, Pad(GamepadButtonType::RightTrigger2)],
+            Action::ZoomOut => vec![Key(KeyCode::ArrowDown), ScrollDown, Pad(GamepadButtonType::LeftTrigger2)],
+            Action::SpeedUp => vec![Key(KeyCode::ArrowRight), Pad(GamepadButtonType::DPadRight)],
+            Action::SpeedDown => vec![Key(KeyCode::ArrowLeft), Pad(GamepadButtonType::DPadLeft)],
+            Action::Pause => vec![Key(KeyCode::Space), Pad(GamepadButtonType::Start)],
+            Action::Select => vec![Mouse(MouseButton::Left), Pad(GamepadButtonType::South)],
+            Action::Delete => vec![Key(KeyCode::Delete), Pad(GamepadButtonType::West)],
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::ZoomIn => write!(f, "Zoom In"),
+            Action::ZoomOut => write!(f, "Zoom Out"),
+            Action::SpeedUp => write!(f, "Speed Up"),
+            Action::SpeedDown => write!(f, "Speed Down"),
+            _ => fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Something that can trigger an action, written in the input file as e.g. "Key Space",
+/// "Mouse Right", "Scroll Up", "Pad South" or "Stick Left".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+    Pad(GamepadButtonType),
+    Stick(Side),
+}
+
+// What a binding is pressed with; rebinding only replaces bindings of the same kind
+#[derive(PartialEq, Eq)]
+enum Device {
+    Keyboard,
+    MouseButtons,
+    Wheel,
+    PadButtons,
+    Sticks,
+}
+
+impl Binding {
+    fn device(&self) -> Device {
+        match self {
+            Binding::Key(_) => Device::Keyboard,
+            Binding::Mouse(_) => Device::MouseButtons,
+            Binding::ScrollUp | Binding::ScrollDown => Device::Wheel,
+            Binding::Pad(_) => Device::PadButtons,
+            Binding::Stick(_) => Device::Sticks,
+        }
+    }
+}
+
+// Keys that can be named in the input file
+const KEYS: &[KeyCode] = &[
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF,
+    KeyCode::KeyG, KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::KeyM, KeyCode::KeyN, KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR,
+    KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU, KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX,
+    KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Escape, KeyCode::Tab, KeyCode::Backspace,
+    KeyCode::Delete, KeyCode::Insert, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight,
+    KeyCode::AltLeft, KeyCode::AltRight,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::BracketLeft, KeyCode::BracketRight, KeyCode::Semicolon,
+    KeyCode::Quote, KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Backslash, KeyCode::Backquote,
+];
+const MOUSE_BUTTONS: [MouseButton; 5] = [
+    MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::Back, MouseButton::Forward,
+];
+const PAD_BUTTONS: [GamepadButtonType; 17] = [
+    GamepadButtonType::South, GamepadButtonType::East, GamepadButtonType::North, GamepadButtonType::West,
+    GamepadButtonType::LeftTrigger, GamepadButtonType::LeftTrigger2, GamepadButtonType::RightTrigger,
+    GamepadButtonType::RightTrigger2, GamepadButtonType::Select, GamepadButtonType::Start,
+    GamepadButtonType::Mode, GamepadButtonType::LeftThumb, GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp, GamepadButtonType::DPadDown, GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+/// Whether `key` can be written in the input file, and so bound.
+pub fn nameable_key(key: KeyCode) -> bool {
+    KEYS.contains(&key)
+}
+
+fn by_name<T: Copy + fmt::Debug>(options: &[T], name: &str) -> Option<T> {
+    options.iter().copied().find(|option| format!("{option:?}").eq_ignore_ascii_case(name))
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let (kind, name) = text.trim().split_once(' ').ok_or(format!("expected a kind and a name in {text:?}"))?;
+        let name = name.trim();
+        let binding = match kind.to_ascii_lowercase().as_str() {
+            "key" => by_name(KEYS, name).map(Binding::Key),
+            "mouse" => by_name(&MOUSE_BUTTONS, name).map(Binding::Mouse),
+            "scroll" if name.eq_ignore_ascii_case("up") => Some(Binding::ScrollUp),
+            "scroll" if name.eq_ignore_ascii_case("down") => Some(Binding::ScrollDown),
+            "pad" => by_name(&PAD_BUTTONS, name).map(Binding::Pad),
+            "stick" => by_name(&[Side::Left, Side::Right], name).map(Binding::Stick),
+            _ => return Err(format!("unknown input kind {kind:?}")),
+        };
+        binding.ok_or(format!("unknown {kind} {name:?}"))
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "Key {key:?}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::ScrollUp => write!(f, "Scroll Up"),
+            Binding::ScrollDown => write!(f, "Scroll Down"),
+            Binding::Pad(button) => write!(f, "Pad {button:?}"),
+            Binding::Stick(side) => write!(f, "Stick {side:?}"),
+        }
+    }
+}
+
+/// Which inputs trigger each action, starting from the defaults with any overrides from
+/// `USER_INPUT_FILE`.
+#[derive(Resource)]
+pub struct InputMap {
+    pub path: PathBuf,
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+    // The action whose next input becomes its binding, while rebinding in the panel
+    pub listening: Option<Action>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from(USER_INPUT_FILE),
+            bindings: Action::ALL.iter().map(|action| (*action, action.defaults())).collect(),
+            listening: None,
+        }
+    }
+}
+
+impl InputMap {
+    /// Replaces the bindings of every action listed in a TOML table of binding lists.
+    pub fn load(&mut self, path: &Path) -> Result<usize, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let file: HashMap<String, Vec<String>> = toml::from_str(&text).map_err(|err| err.to_string())?;
+        let mut count = 0;
+        for (name, bindings) in file {
+            let action = Action::ALL.into_iter().find(|action| action.key() == name).ok_or(format!("unknown action {name}"))?;
+            let bindings = bindings.iter().map(|binding| binding.parse()).collect::<Result<Vec<_>, _>>()?;
+            self.bindings.insert(action, bindings);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let file: BTreeMap<&str, Vec<String>> = self
+            .bindings
+            .iter()
+            .map(|(action, bindings)| (action.key(), bindings.iter().map(ToString::to_string).collect()))
+            .collect();
+        let text = toml::to_string(&file).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, text).map_err(|err| err.to_string())
+    }
+
+    /// Binds `binding` to `action` in place of its bindings of the same kind, so binding
+    /// a key leaves the mouse, wheel and gamepad bindings alone.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        let device = binding.device();
+        // Keeps the new binding where the one it replaces was listed
+        let index = bindings.iter().position(|existing| existing.device() == device).unwrap_or(bindings.len());
+        bindings.retain(|existing| existing.device() != device);
+        bindings.insert(index, binding);
+    }
+}
+
+/// This frame's actions, for systems to read instead of raw input.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    scrolled: HashMap<Action, f32>, // in lines
+    sticks: HashMap<Action, Vec2>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// Scroll wheel lines towards `action` this frame.
+    pub fn scrolled(&self, action: Action) -> f32 {
+        self.scrolled.get(&action).copied().unwrap_or_default()
+    }
+
+    /// Deflection of any stick bound to `action`, right and up positive.
+    pub fn stick(&self, action: Action) -> Vec2 {
+        self.sticks.get(&action).copied().unwrap_or_default()
+    }
+}
+
+/// Systems that read keys directly rather than through actions. They pause while the
+/// panel waits for a new binding, so the input being bound does nothing else.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Shortcuts;
+
+pub fn not_rebinding(input_map: Res<InputMap>) -> bool {
+    input_map.listening.is_none()
+}
+
+pub fn action_just_pressed(action: Action) -> impl FnMut(Res<ActionState>) -> bool + Clone {
+    move |actions: Res<ActionState>| actions.just_pressed(action)
+}
+
+pub fn load_user_input_map(mut input_map: ResMut<InputMap>) {
+    let path = input_map.path.clone();
+    if !path.exists() { return }
+    match input_map.load(&path) {
+        Ok(count) => info!("Loaded bindings for {count} actions from {}", path.display()),
+        Err(err) => warn!("Could not load {}: {err}", path.display()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_actions(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut ev_scroll: EventReader<MouseWheel>,
+    gamepads: Res<Gamepads>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+    pad_axes: Res<Axis<GamepadAxis>>,
+    input_map: Res<InputMap>,
+    mut actions: ResMut<ActionState>,
+) {
+    let scroll: f32 = ev_scroll
+        .read()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 100.0,
+        })
+        .sum();
+    let previous = std::mem::take(&mut actions.pressed);
+    actions.just_pressed.clear();
+    actions.just_released.clear();
+    actions.scrolled.clear();
+    actions.sticks.clear();
+    // Whatever is pressed while rebinding is meant for the new binding, not the scene
+    if input_map.listening.is_some() {
+        actions.just_released.extend(previous);
+        return;
+    }
+
+    let pad_pressed = |button| gamepads.iter().any(|gamepad| pad_buttons.pressed(GamepadButton::new(gamepad, button)));
+    let stick = |side| {
+        let (x, y) = match side {
+            Side::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Side::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        };
+        gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |kind| pad_axes.get(GamepadAxis::new(gamepad, kind)).unwrap_or_default();
+                Vec2::new(axis(x), axis(y))
+            })
+            .find(|deflection| deflection.length() > STICK_DEADZONE)
+            .unwrap_or_default()
+    };
+
+    for (action, bindings) in &input_map.bindings {
+        let mut held = false;
+        for binding in bindings {
+            held |= match *binding {
+                Binding::Key(key) => keyboard.pressed(key),
+                Binding::Mouse(button) => mouse.pressed(button),
+                Binding::Pad(button) => pad_pressed(button),
+                Binding::ScrollUp | Binding::ScrollDown | Binding::Stick(_) => false,
+            };
+            match *binding {
+                Binding::ScrollUp if scroll > 0.0 => *actions.scrolled.entry(*action).or_default() += scroll,
+                Binding::ScrollDown if scroll < 0.0 => *actions.scrolled.entry(*action).or_default() -= scroll,
+                Binding::Stick(side) => *actions.sticks.entry(*action).or_default() += stick(side),
+                _ => {}
+            }
+        }
+        if held {
+            actions.pressed.insert(*action);
+            if !previous.contains(action) {
+                actions.just_pressed.insert(*action);
+            }
+        } else if previous.contains(action) {
+            actions.just_released.insert(*action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+
+    #[test]
+    fn bindings_read_back_as_written() {
+        for binding in Action::ALL.iter().flat_map(|action| action.defaults()) {
+            assert_eq!(binding.to_string().parse::<Binding>(), Ok(binding));
+        }
+        assert_eq!("key space".parse::<Binding>(), Ok(Binding::Key(KeyCode::Space)));
+        assert_eq!("  Scroll  down ".parse::<Binding>(), Ok(Binding::ScrollDown));
+        assert_eq!(Binding::Pad(GamepadButtonType::DPadLeft).to_string(), "Pad DPadLeft");
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        assert_eq!("Key Fnord".parse::<Binding>(), Err("unknown Key \"Fnord\"".to_string()));
+        assert_eq!("Joystick A".parse::<Binding>(), Err("unknown input kind \"Joystick\"".to_string()));
+        assert!("Space".parse::<Binding>().is_err());
+        assert!("Scroll Left".parse::<Binding>().is_err());
+        // Keys that cannot be written down cannot be bound either
+        assert!(!nameable_key(KeyCode::Fn));
+        assert!("Key Fn".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn rebinding_replaces_only_the_same_kind() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(Action::ZoomIn, Binding::Key(KeyCode::KeyW));
+        assert_eq!(
+            input_map.bindings[&Action::ZoomIn],
+            [Binding::Key(KeyCode::KeyW), Binding::ScrollUp, Binding::Pad(GamepadButtonType::RightTrigger2)]
+        );
+        input_map.rebind(Action::Orbit, Binding::Key(KeyCode::KeyO));
+        assert_eq!(
+            input_map.bindings[&Action::Orbit],
+            [Binding::Mouse(MouseButton::Right), Binding::Stick(Side::Right), Binding::Key(KeyCode::KeyO)]
+        );
+    }
+
+    #[test]
+    fn input_files_override_only_what_they_list() {
+        let path = std::env::temp_dir().join(format!("input-{}.toml", std::process::id()));
+        std::fs::write(&path, "speed_up = [\"Key KeyD\", \"Pad East\"]\n").unwrap();
+        let mut input_map = InputMap::default();
+        assert_eq!(input_map.load(&path), Ok(1));
+        assert_eq!(input_map.bindings[&Action::SpeedUp], [Binding::Key(KeyCode::KeyD), Binding::Pad(GamepadButtonType::East)]);
+        assert_eq!(input_map.bindings[&Action::Pause], Action::Pause.defaults());
+
+        std::fs::write(&path, "faster = [\"Key KeyD\"]\n").unwrap();
+        assert_eq!(InputMap::default().load(&path), Err("unknown action faster".to_string()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn pressing_a_bound_key_presses_its_action() {
+        let mut world = World::new();
+        world.init_resource::<ButtonInput<KeyCode>>();
+        world.init_resource::<ButtonInput<MouseButton>>();
+        world.init_resource::<Events<MouseWheel>>();
+        world.init_resource::<Gamepads>();
+        world.init_resource::<ButtonInput<GamepadButton>>();
+        world.init_resource::<Axis<GamepadAxis>>();
+        world.init_resource::<ActionState>();
+        let mut input_map = InputMap::default();
+        input_map.rebind(Action::Pause, Binding::Key(KeyCode::KeyP));
+        world.insert_resource(input_map);
+
+        world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyP);
+        world.run_system_once(update_actions);
+        let actions = world.resource::<ActionState>();
+        assert!(actions.pressed(Action::Pause) && actions.just_pressed(Action::Pause));
+        assert!(!actions.pressed(Action::SpeedUp));
+
+        // The old key no longer does anything
+        world.resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::KeyP);
+        world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Space);
+        world.run_system_once(update_actions);
+        let actions = world.resource::<ActionState>();
+        assert!(!actions.pressed(Action::Pause) && actions.just_released(Action::Pause));
+    }
+}
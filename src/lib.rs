@@ -0,0 +1,70 @@
+//! The Fundamentals visualizer as Bevy plugins, so it can run inside another app.
+//!
+//! `AudioSimPlugin` is the simulation, audio output and analysis, `OrbitCameraPlugin` the
+//! camera rigs and picking, `ControlPanelPlugin` the side panel and `VisualizationPlugin`
+//! the gizmos and overlays drawn over the scene. The app spawns its own camera (with
+//! `OrbitCamera` and `FlyCamera`), emitters and walls.
+pub mod acoustics {
+    pub mod material;
+    pub mod medium;
+    pub mod modes;
+    pub mod occlusion;
+    pub mod propagation;
+    pub mod raytrace;
+    pub mod room;
+}
+pub mod audio {
+    pub mod convolver;
+    pub mod engine;
+    pub mod filter;
+    pub mod harmony;
+    pub mod impulse;
+    pub mod loudness;
+    pub mod microphone;
+    pub mod offline;
+    pub mod pitch;
+    pub mod rhythm;
+    pub mod spectrum;
+}
+pub mod camera {
+    pub mod bookmarks;
+    pub mod fly;
+    pub mod focus;
+    pub mod orbit;
+    pub mod selection;
+}
+pub mod extras {
+    pub mod components;
+    pub mod raycast;
+    pub mod resources;
+}
+pub mod input {
+    pub mod map;
+}
+pub mod music {
+    pub mod chord;
+    pub mod note;
+    pub mod roughness;
+    pub mod timbre;
+    pub mod tuning;
+}
+pub mod scene {
+    pub mod layout;
+}
+pub mod settings;
+pub mod simulation;
+pub mod ui {
+    pub mod bindings;
+    pub mod loudness;
+    pub mod microphones;
+    pub mod panel;
+    pub mod readouts;
+}
+pub mod visuals {
+    pub mod partials;
+    pub mod rhythm;
+}
+mod plugins;
+
+pub use extras::{components, resources};
+pub use plugins::*;
@@ -1,59 +1,120 @@
 use bevy::prelude::*;
-use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
-use bevy::window::CursorGrabMode;
+use bevy::window::PrimaryWindow;
+use cli::{Command, Headless, USAGE};
+use fundamentals::acoustics::material::MaterialLibrary;
+use fundamentals::acoustics::room::Wall;
+use fundamentals::audio::engine::{output_device_names, AudioSettings};
+use fundamentals::audio::microphone::{Microphone, EAR_HEIGHT};
+use fundamentals::camera::fly::FlyCamera;
+use fundamentals::camera::orbit::OrbitCamera;
+use fundamentals::components::*;
+use fundamentals::extras::raycast::SphereBounds;
+use fundamentals::music::note::Pitch;
+use fundamentals::music::timbre::Instrument;
+use fundamentals::music::tuning::{TunedNote, Tuning};
+use fundamentals::resources::SimulationTime;
+use fundamentals::scene::layout::{load_scene_layout, SceneLayout};
+use fundamentals::settings::load_user_settings;
+use fundamentals::simulation::RotatingSphere;
+use fundamentals::visuals::rhythm::Flash;
+use fundamentals::{
+    AudioSimPlugin, ControlPanelPlugin, HeadlessPlugin, OrbitCameraPlugin, SettingsPlugin, VisualizationPlugin,
+};
 use std::f32::consts::PI;
 
+mod cli;
+
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Fundamental".into(),
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(options)) => options,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(Command::ListAudioDevices) => {
+            for name in output_device_names() {
+                println!("{name}");
+            }
+            return;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\nRun with --help for the options");
+            std::process::exit(2);
+        }
+    };
+
+    let mut app = App::new();
+    let windowed = options.headless.is_none();
+    match options.headless {
+        Some(Headless { ticks, render }) => {
+            // Saved settings are left out so a run only depends on its options
+            app.insert_resource(SimulationTime {
+                    speed_multiplier: options.speed.unwrap_or(SimulationTime::default().speed_multiplier),
+                    ..default()
+                })
+                .add_plugins(HeadlessPlugin { ticks, render })
+                // The scene's meshes and materials are kept but never drawn
+                .init_resource::<Assets<Mesh>>()
+                .init_resource::<Assets<StandardMaterial>>();
+        }
+        None => {
+            app.add_plugins(DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Fundamental".into(),
+                    ..default()
+                }),
                 ..default()
-            }),
-            ..default()
-        }))
-        .add_systems(Startup, setup)
-        .add_systems(Update, (
-                rotate_sphere,
-                camera_controller,
-        ))
-        .run();
-}
-
-#[derive(Component)]
-struct RotatingSphere;
-#[derive(Component)]
-struct OrbitCamera {
-    focus: Vec3,
-    radius: f32,
-    upside_down: bool,
-}
-
-impl Default for OrbitCamera {
-    fn default() -> Self {
-        Self {
-            focus: Vec3::ZERO,
-            radius: 10.0,
-            upside_down: false,
+            }));
+            // Options on the command line win over the saved settings for this run only
+            let settings = load_user_settings();
+            if let Some(speed) = options.speed {
+                app.insert_resource(SimulationTime { speed_multiplier: speed, ..default() });
+            }
+            if options.audio_device.is_some() || options.sample_rate.is_some() {
+                app.insert_resource(AudioSettings {
+                    device: options.audio_device.or(settings.audio.device.clone()),
+                    sample_rate: options.sample_rate.or(settings.audio.sample_rate),
+                });
+            }
+            let mut window = settings.window.clone();
+            app.insert_resource(settings).add_plugins(SettingsPlugin);
+            if options.window.is_some() || options.fullscreen {
+                if let Some((width, height)) = options.window {
+                    (window.width, window.height) = (width as f32, height as f32);
+                }
+                window.fullscreen = options.fullscreen;
+                let mut windows = app.world.query_filtered::<&mut Window, With<PrimaryWindow>>();
+                if let Ok(mut primary) = windows.get_single_mut(&mut app.world) {
+                    window.apply(&mut primary);
+                }
+            }
         }
     }
+    // Read once logging is up, and before the plugins build the room from it
+    if let Some(path) = &options.scene {
+        app.insert_resource(load_scene_layout(path));
+    }
+    app.add_plugins(AudioSimPlugin);
+    if windowed {
+        app.add_plugins((OrbitCameraPlugin, ControlPanelPlugin, VisualizationPlugin));
+    }
+    app.add_systems(Startup, setup).run();
 }
 
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    (library, layout): (Res<MaterialLibrary>, Res<SceneLayout>),
+    tuning: Res<Tuning>,
 ) {
     commands.spawn((
         Camera3dBundle {
-            transform: Transform::from_xyz(0.0, 5.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            transform: OrbitCamera::default().transform(),
             ..default()
         },
-        OrbitCamera {
-            focus: Vec3::ZERO,
-            radius: 0.0,
-            upside_down: false,
-        }
+        OrbitCamera::default(),
+        FlyCamera::default(),
     ));
 
     commands.spawn(DirectionalLightBundle {
@@ -71,16 +132,30 @@ fn setup(
         brightness: 0.2,
     });
 
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(Plane3d::default().mesh().size(10.0, 10.0)),
-        material: materials.add(StandardMaterial {
-            base_color: Color::rgb(0.3, 0.5, 0.3),
-            perceptual_roughness: 1.0,
+    let material = |name: &str| {
+        library.get(name).cloned().unwrap_or_else(|| {
+            warn!("No material called {name:?}, using the default");
+            default()
+        })
+    };
+
+    // Spans the room floor so it takes every floor reflection
+    let [width, _, depth] = layout.room.size;
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Plane3d::default().mesh().size(width, depth)),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.3, 0.5, 0.3),
+                perceptual_roughness: 1.0,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, 0.0, 0.0),
             ..default()
-        }),
-        transform: Transform::from_xyz(0.0, 0.0, 0.0),
-        ..default()
-    });
+        },
+        Wall::Panel { half_size: Vec2::new(width, depth) / 2.0 },
+        material(&layout.room.ground),
+        Ground,
+    ));
 
     commands.spawn((
         PbrBundle {
@@ -95,90 +170,70 @@ fn setup(
             ..default()
         },
         RotatingSphere,
+        Selectable,
+        SphereBounds { radius: 0.5 },
     ));
-}
-
-fn rotate_sphere(
-    time: Res<Time>,
-    mut query: Query<&mut Transform, With<RotatingSphere>>,
-) {
-    for mut transform in &mut query {
-        let angle = time.elapsed_seconds() * PI / 2.0;
-        let radius = 2.0;
-        let x = radius * angle.cos();
-        let z = radius * angle.sin();
 
-        transform.translation.x = x;
-        transform.translation.z = z;        
-        transform.rotate_y(time.delta_seconds() * 2.0);
-    }
-}
-
-fn camera_controller(
-    mut ev_motion: EventReader<MouseMotion>,
-    mut ev_scroll: EventReader<MouseWheel>,
-    input_mouse: Res<ButtonInput<MouseButton>>,
-    mut query: Query<(&mut Transform, &mut OrbitCamera)>,
-    mut window_query: Query<&mut Window>,
-) {
-    let mut window = window_query.single_mut();
-    let mut rotation_move = Vec2::ZERO;
-    let mut scroll = 0.0;
-    let mut orbit_button_pressed = false;
-
-    if input_mouse.pressed(MouseButton::Right) {
-        for ev in ev_motion.read() {
-            rotation_move += ev.delta;
-        }
-        orbit_button_pressed = true;
+    for wall in &layout.walls {
+        let [width, height] = wall.size;
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Plane3d::default().mesh().size(width, height)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.55, 0.4, 0.25),
+                    double_sided: true,
+                    cull_mode: None,
+                    ..default()
+                }),
+                transform: Transform::from_translation(Vec3::from(wall.center))
+                    .with_rotation(Quat::from_rotation_y(wall.yaw.to_radians()) * Quat::from_rotation_x(PI / 2.0)),
+                ..default()
+            },
+            Wall::Panel { half_size: Vec2::new(width, height) / 2.0 },
+            material(&wall.material),
+        ));
     }
 
-    for ev in ev_scroll.read() {
-        scroll = match ev.unit {
-            MouseScrollUnit::Line => ev.y,
-            MouseScrollUnit::Pixel => ev.y / 100.0,
+    for emitter in &layout.emitters {
+        let pitch: Pitch = match emitter.note.parse() {
+            Ok(pitch) => pitch,
+            Err(err) => {
+                warn!("Skipping an emitter in {}: {err}", layout.path.display());
+                continue;
+            }
         };
+        let [r, g, b] = emitter.color;
+        let color = Color::rgb(r, g, b);
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Sphere::new(0.5).mesh()),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    emissive: color * 0.2,
+                    ..default()
+                }),
+                transform: Transform::from_translation(Vec3::from(emitter.position)),
+                ..default()
+            },
+            AudioEmitter {
+                frequency: tuning.frequency(pitch),
+                amplitude: 0.3, // scale range of 0.7 to 1.3
+                phase: emitter.phase,
+                partials: Instrument::Sine.partials(),
+            },
+            Instrument::Sine,
+            TunedNote { pitch },
+            Flash::default(),
+            Selectable,
+            SphereBounds { radius: 0.5 },
+        ));
     }
 
-    if input_mouse.just_pressed(MouseButton::Right) {
-        window.cursor.grab_mode = CursorGrabMode::Locked;
-        window.cursor.visible = false;
-    }
-    if input_mouse.just_released(MouseButton::Right) {
-        window.cursor.grab_mode = CursorGrabMode::None;
-        window.cursor.visible = true;
-    }
-
-    for (mut transform, mut orbit) in query.iter_mut() {
-        if orbit_button_pressed {
-            let sensitivity = 0.5;
-            let delta_x = rotation_move.x * sensitivity;
-            let delta_y = rotation_move.y * sensitivity;
-            let mut position = transform.translation - orbit.focus;
-            let mut rotation = transform.rotation;
-
-            let yaw = Quat::from_rotation_y(-delta_x * 0.005);
-            position = yaw * position;
-            rotation = yaw * rotation;
-
-            let pitch = Quat::from_rotation_x(-delta_y * 0.005);
-            position = pitch * position;
-            rotation = pitch * rotation;
-
-            let up = rotation * Vec3::Y;
-            orbit.upside_down = up.y <= 0.0;
-
-            transform.translation = position + orbit.focus;
-            transform.rotation = rotation;
-        }
-        if scroll != 0.0 {
-            let scroll_sensitivity = 0.1;
-            orbit.radius -= scroll * scroll_sensitivity;
-            orbit.radius = orbit.radius.max(1.0).min(50.0);
-
-            let direction = (transform.translation - orbit.focus).normalize();
-            transform.translation = orbit.focus + direction * orbit.radius;
-        }
-        transform.look_at(orbit.focus, Vec3::Y);
+    for microphone in &layout.microphones {
+        let [x, z] = microphone.position;
+        commands.spawn((
+            TransformBundle::from_transform(Transform::from_xyz(x, EAR_HEIGHT, z)),
+            Microphone { name: microphone.name.clone() },
+        ));
     }
 }
@@ -0,0 +1,157 @@
+use std::fmt;
+use crate::music::note::Note;
+
+// Out-of-tune notes further than this from a semitone step are not named
+pub const DEFAULT_TOLERANCE: f32 = 35.0; // in cents
+
+const INTERVALS: [&str; 12] = [
+    "unison",
+    "minor second",
+    "major second",
+    "minor third",
+    "major third",
+    "perfect fourth",
+    "tritone",
+    "perfect fifth",
+    "minor sixth",
+    "major sixth",
+    "minor seventh",
+    "major seventh",
+];
+
+// Chord tones in semitones above the root, in stacking order so the index is the inversion
+const CHORDS: [(&str, &[i32]); 12] = [
+    ("major", &[0, 4, 7]),
+    ("minor", &[0, 3, 7]),
+    ("diminished", &[0, 3, 6]),
+    ("augmented", &[0, 4, 8]),
+    ("sus2", &[0, 2, 7]),
+    ("sus4", &[0, 5, 7]),
+    ("dominant 7th", &[0, 4, 7, 10]),
+    ("major 7th", &[0, 4, 7, 11]),
+    ("minor 7th", &[0, 3, 7, 10]),
+    ("minor major 7th", &[0, 3, 7, 11]),
+    ("half-diminished 7th", &[0, 3, 6, 10]),
+    ("diminished 7th", &[0, 3, 6, 9]),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Harmony {
+    Note(Note),
+    Interval { lower: Note, semitones: i32 },
+    Chord { root: Note, quality: &'static str, inversion: usize },
+}
+
+impl fmt::Display for Harmony {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Harmony::Note(note) => write!(f, "{note}"),
+            Harmony::Interval { lower, semitones } => {
+                let compound = if *semitones > 12 { "compound " } else { "" };
+                let name = match semitones {
+                    12 => "octave",
+                    _ => INTERVALS[semitones.rem_euclid(12) as usize],
+                };
+                write!(f, "{compound}{name} above {lower}")
+            }
+            Harmony::Chord { root, quality, inversion } => {
+                write!(f, "{} {quality}", root.name())?;
+                match inversion {
+                    0 => Ok(()),
+                    1 => write!(f, ", 1st inversion"),
+                    2 => write!(f, ", 2nd inversion"),
+                    _ => write!(f, ", 3rd inversion"),
+                }
+            }
+        }
+    }
+}
+
+/// Names the note, interval or chord formed by `frequencies`. Steps are measured from the
+/// lowest frequency, so a set that is detuned as a whole is still recognised; returns the
+/// harmony and the largest deviation from a whole semitone, in cents.
+pub fn recognize(frequencies: &[f32], tolerance: f32) -> Option<(Harmony, f32)> {
+    let mut frequencies: Vec<f32> = frequencies.iter().copied().filter(|f| *f > 0.0).collect();
+    frequencies.sort_by(f32::total_cmp);
+    let bass = *frequencies.first()?;
+    let (bass_note, _) = Note::nearest(bass);
+
+    let mut steps = Vec::new();
+    let mut detune = 0.0f32;
+    for frequency in &frequencies {
+        let exact = 12.0 * (frequency / bass).log2();
+        let step = exact.round();
+        let off = 100.0 * (exact - step).abs();
+        if off > tolerance {
+            return None;
+        }
+        detune = detune.max(off);
+        steps.push(step as i32);
+    }
+    steps.dedup();
+    let mut classes: Vec<i32> = steps.iter().map(|step| step.rem_euclid(12)).collect();
+    classes.sort();
+    classes.dedup();
+
+    let harmony = match classes.len() {
+        1 if steps.len() == 1 => Harmony::Note(bass_note),
+        // Octaves of one pitch class
+        1 => Harmony::Interval { lower: bass_note, semitones: steps[1] },
+        2 => {
+            let upper = steps.iter().find(|step| step.rem_euclid(12) != 0)?;
+            Harmony::Interval { lower: bass_note, semitones: *upper }
+        }
+        // Trying the bass first keeps symmetric chords rooted on it
+        _ => classes.iter().find_map(|&root| {
+            let mut tones: Vec<i32> = classes.iter().map(|class| (class - root).rem_euclid(12)).collect();
+            tones.sort();
+            let (quality, template) = CHORDS.iter().find(|(_, template)| *template == tones.as_slice())?;
+            let inversion = template.iter().position(|tone| *tone == (-root).rem_euclid(12))?;
+            Some(Harmony::Chord { root: Note { midi: bass_note.midi + root }, quality, inversion })
+        })?,
+    };
+    Some((harmony, detune))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hz(midi: i32) -> f32 {
+        440.0 * 2f32.powf((midi - 69) as f32 / 12.0)
+    }
+
+    fn name(midis: &[i32]) -> Option<String> {
+        let frequencies: Vec<f32> = midis.iter().map(|midi| hz(*midi)).collect();
+        recognize(&frequencies, DEFAULT_TOLERANCE).map(|(harmony, _)| harmony.to_string())
+    }
+
+    #[test]
+    fn triads_and_sevenths_are_named_with_their_inversion() {
+        assert_eq!(name(&[69, 72, 76]).as_deref(), Some("A minor"));
+        assert_eq!(name(&[64, 69, 72]).as_deref(), Some("A minor, 2nd inversion"));
+        assert_eq!(name(&[64, 67, 72]).as_deref(), Some("C major, 1st inversion"));
+        assert_eq!(name(&[67, 71, 74, 77]).as_deref(), Some("G dominant 7th"));
+        assert_eq!(name(&[60, 64, 67, 72, 76]).as_deref(), Some("C major"));
+    }
+
+    #[test]
+    fn notes_and_intervals_are_named_from_the_bass() {
+        assert_eq!(name(&[69]).as_deref(), Some("A4"));
+        assert_eq!(name(&[60, 67]).as_deref(), Some("perfect fifth above C4"));
+        assert_eq!(name(&[60, 72]).as_deref(), Some("octave above C4"));
+        assert_eq!(name(&[60, 76]).as_deref(), Some("compound major third above C4"));
+        assert_eq!(name(&[]), None);
+    }
+
+    #[test]
+    fn detuning_is_measured_and_limited_by_the_tolerance() {
+        let just_minor = [440.0, 528.0, 660.0];
+        let (harmony, detune) = recognize(&just_minor, DEFAULT_TOLERANCE).unwrap();
+        assert_eq!(harmony.to_string(), "A minor");
+        // The just minor third is about 16 cents wider than the tempered one
+        assert!((detune - 15.6).abs() < 0.5, "{detune} cents");
+        assert!(recognize(&just_minor, 10.0).is_none());
+        assert!(recognize(&[440.0, 453.0], DEFAULT_TOLERANCE).is_none());
+    }
+}
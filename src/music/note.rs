@@ -0,0 +1,122 @@
+use std::fmt;
+use std::str::FromStr;
+
+const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+pub const A4_MIDI: i32 = 69;
+pub const A4_FREQUENCY: f32 = 440.0;
+
+/// A twelve-tone equal tempered pitch, numbered like MIDI so A4 is 69.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Note {
+    pub midi: i32,
+}
+
+impl Note {
+    pub fn name(&self) -> &'static str {
+        NAMES[self.midi.rem_euclid(12) as usize]
+    }
+
+    pub fn octave(&self) -> i32 {
+        self.midi.div_euclid(12) - 1
+    }
+
+    /// The closest note to `frequency` and how far off it is, in cents.
+    pub fn nearest(frequency: f32) -> (Self, f32) {
+        let semitones = 12.0 * (frequency / A4_FREQUENCY).log2();
+        let midi = semitones.round() as i32 + A4_MIDI;
+        let cents = 100.0 * (semitones - (midi - A4_MIDI) as f32);
+        (Self { midi }, cents)
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.name(), self.octave())
+    }
+}
+
+/// A note with an optional offset in cents, written like `A4`, `Bb3` or `C5+12c`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pitch {
+    pub note: Note,
+    pub cents: f32,
+}
+
+impl FromStr for Pitch {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let mut chars = text.chars();
+        let letter = chars.next().ok_or("empty note")?;
+        let mut class = match letter.to_ascii_uppercase() {
+            'C' => 0,
+            'D' => 2,
+            'E' => 4,
+            'F' => 5,
+            'G' => 7,
+            'A' => 9,
+            'B' => 11,
+            _ => return Err(format!("{text}: no note letter")),
+        };
+        let rest = chars.as_str();
+        let rest = match rest.chars().next() {
+            Some('#') => {
+                class += 1;
+                &rest[1..]
+            }
+            Some('b') => {
+                class -= 1;
+                &rest[1..]
+            }
+            _ => rest,
+        };
+        // The octave ends where the cents start, a leading minus belongs to the octave
+        let split = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '+' || *c == '-')
+            .map_or(rest.len(), |(index, _)| index);
+        let (octave, cents) = rest.split_at(split);
+        let octave: i32 = octave.parse().map_err(|_| format!("{text}: no octave"))?;
+        let cents = match cents.trim_end_matches('c') {
+            "" => 0.0,
+            offset => offset.parse().map_err(|_| format!("{text}: bad cents"))?,
+        };
+        Ok(Self { note: Note { midi: (octave + 1) * 12 + class }, cents })
+    }
+}
+
+impl fmt::Display for Pitch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.note)?;
+        if self.cents != 0.0 {
+            write!(f, "{:+}c", self.cents)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pitches_parse_with_accidentals_octaves_and_cents() {
+        let pitch = |text: &str| text.parse::<Pitch>().unwrap();
+        assert_eq!(pitch("A4"), Pitch { note: Note { midi: 69 }, cents: 0.0 });
+        assert_eq!(pitch("c#5").note.midi, 73);
+        assert_eq!(pitch("Bb3").note.midi, 58);
+        assert_eq!(pitch("C-1").note.midi, 0);
+        assert_eq!(pitch("C5+12c"), Pitch { note: Note { midi: 72 }, cents: 12.0 });
+        assert_eq!(pitch("E4-30").cents, -30.0);
+        assert_eq!(pitch("C5+12c").to_string(), "C5+12c");
+    }
+
+    #[test]
+    fn malformed_pitches_are_rejected() {
+        for text in ["", "H4", "A", "A4+x"] {
+            assert!(text.parse::<Pitch>().is_err(), "{text:?} parsed");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use crate::extras::components::{AudioEmitter, Harmonic};
+use crate::music::note::Note;
+
+// Highest harmonic checked for coinciding partials
+const MAX_HARMONIC: u32 = 8;
+// Slower than this between coinciding partials is heard as beating rather than roughness
+const MAX_BEAT: f32 = 20.0; // in Hz
+// Sine emitters only roughen within a critical band, so pairs of them are scored as if
+// they had a sawtooth-like harmonic timbre, as in Sethares' examples
+const ASSUMED_HARMONICS: usize = 6;
+const HARMONIC_ROLLOFF: f32 = 0.88;
+// Faster pulsing than this would only alias against the frame rate
+const MAX_DRAWN_BEAT: f32 = 12.0; // in Hz
+
+/// Harmonics `p` of the lower and `q` of the upper tone that nearly coincide, and how fast
+/// they beat against each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Beat {
+    pub lower_harmonic: u32,
+    pub upper_harmonic: u32,
+    pub frequency: f32, // in Hz
+}
+
+/// The simplest pair of harmonics of `lower` and `upper` close enough to beat, if any.
+pub fn beat_frequency(lower: f32, upper: f32) -> Option<Beat> {
+    let (lower, upper) = (lower.min(upper), lower.max(upper));
+    (1..=MAX_HARMONIC).find_map(|upper_harmonic| {
+        let lower_harmonic = (upper_harmonic as f32 * upper / lower).round() as u32;
+        let frequency = (lower_harmonic as f32 * lower - upper_harmonic as f32 * upper).abs();
+        ((1..=MAX_HARMONIC).contains(&lower_harmonic) && frequency < MAX_BEAT)
+            .then_some(Beat { lower_harmonic, upper_harmonic, frequency })
+    })
+}
+
+/// Plomp-Levelt dissonance of two sets of (frequency, amplitude) partials, using Sethares'
+/// parameterisation of the curve.
+pub fn dissonance(a: &[(f32, f32)], b: &[(f32, f32)]) -> f32 {
+    let mut total = 0.0;
+    for &(f1, a1) in a {
+        for &(f2, a2) in b {
+            let s = 0.24 / (0.0207 * f1.min(f2) + 18.96);
+            let difference = (f1 - f2).abs();
+            total += a1 * a2 * ((-3.51 * s * difference).exp() - (-5.75 * s * difference).exp());
+        }
+    }
+    total
+}
+
+fn assumed_timbre() -> Vec<Harmonic> {
+    (1..=ASSUMED_HARMONICS as u32).map(|k| Harmonic::nth(k, HARMONIC_ROLLOFF.powi(k as i32 - 1))).collect()
+}
+
+/// (frequency, amplitude) partials of `timbre` on `frequency`.
+fn spectrum(frequency: f32, timbre: &[Harmonic]) -> Vec<(f32, f32)> {
+    timbre.iter().map(|partial| (frequency * partial.ratio, partial.amplitude)).collect()
+}
+
+/// Roughness of two tones relative to a semitone at the same register with the same
+/// timbres, 0 for smooth and around 1 for as rough as a minor second.
+pub fn roughness(lower: f32, lower_timbre: &[Harmonic], upper: f32, upper_timbre: &[Harmonic]) -> f32 {
+    let semitone = lower * 2f32.powf(1.0 / 12.0);
+    let reference = dissonance(&spectrum(lower, lower_timbre), &spectrum(semitone, upper_timbre));
+    let pair = dissonance(&spectrum(lower, lower_timbre), &spectrum(upper, upper_timbre));
+    if reference > 0.0 { (pair / reference).clamp(0.0, 1.0) } else { 0.0 }
+}
+
+#[derive(Clone, Debug)]
+pub struct EmitterPair {
+    pub entities: (Entity, Entity),
+    pub beat: Option<Beat>,
+    pub roughness: f32,
+}
+
+#[derive(Resource)]
+pub struct PairAnalysis {
+    pub pairs: Vec<EmitterPair>,
+    pub visible: bool,
+}
+
+impl Default for PairAnalysis {
+    fn default() -> Self {
+        Self {
+            pairs: Vec::new(),
+            visible: true,
+        }
+    }
+}
+
+pub fn analyze_emitter_pairs(
+    emitter_query: Query<(Entity, Ref<AudioEmitter>)>,
+    mut analysis: ResMut<PairAnalysis>,
+) {
+    let emitters: Vec<_> = emitter_query.iter().collect();
+    let changed = emitters.iter().any(|(_, emitter)| emitter.is_changed());
+    let timbre = |emitter: &AudioEmitter| {
+        if emitter.partials.len() > 1 { emitter.partials.clone() } else { assumed_timbre() }
+    };
+    analysis.pairs.clear();
+    for (i, (a, first)) in emitters.iter().enumerate() {
+        for (b, second) in &emitters[i + 1..] {
+            let (below, above) = if first.frequency <= second.frequency { (first, second) } else { (second, first) };
+            let (lower, upper) = (below.frequency, above.frequency);
+            let pair = EmitterPair {
+                entities: (*a, *b),
+                beat: beat_frequency(lower, upper),
+                roughness: roughness(lower, &timbre(below), upper, &timbre(above)),
+            };
+            if changed {
+                let (low, high) = (Note::nearest(lower).0, Note::nearest(upper).0);
+                match pair.beat {
+                    Some(beat) => info!(
+                        "{low}-{high}: harmonics {}:{} beat at {:.2} Hz, roughness {:.2}",
+                        beat.lower_harmonic, beat.upper_harmonic, beat.frequency, pair.roughness,
+                    ),
+                    None => info!("{low}-{high}: no slow beats, roughness {:.2}", pair.roughness),
+                }
+            }
+            analysis.pairs.push(pair);
+        }
+    }
+}
+
+pub fn toggle_pair_lines(mut analysis: ResMut<PairAnalysis>) {
+    analysis.visible = !analysis.visible;
+}
+
+/// Lines between emitters, green when smooth through yellow to red when rough, pulsing at
+/// their beat frequency.
+pub fn draw_emitter_pairs(
+    time: Res<Time>,
+    analysis: Res<PairAnalysis>,
+    transforms: Query<&GlobalTransform, With<AudioEmitter>>,
+    mut gizmos: Gizmos,
+) {
+    if !analysis.visible { return }
+    for pair in &analysis.pairs {
+        let (Ok(a), Ok(b)) = (transforms.get(pair.entities.0), transforms.get(pair.entities.1)) else {
+            continue;
+        };
+        let color = if pair.roughness < 0.5 {
+            Color::GREEN * (1.0 - 2.0 * pair.roughness) + Color::YELLOW * (2.0 * pair.roughness)
+        } else {
+            Color::YELLOW * (2.0 - 2.0 * pair.roughness) + Color::RED * (2.0 * pair.roughness - 1.0)
+        };
+        let pulse = match pair.beat {
+            Some(beat) if beat.frequency <= MAX_DRAWN_BEAT => {
+                0.6 + 0.4 * (TAU * beat.frequency * time.elapsed_seconds()).cos()
+            }
+            _ => 1.0,
+        };
+        gizmos.line(a.translation(), b.translation(), color.with_a(pulse));
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+use std::fmt;
+use crate::extras::components::{AudioEmitter, Harmonic};
+use crate::extras::resources::SelectionState;
+
+/// Spectra loosely after textbook measurements of each instrument's steady tone.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Instrument {
+    #[default]
+    Sine,
+    Flute,
+    Clarinet,
+    Violin,
+    Trumpet,
+    Organ,
+    Bell,
+}
+
+impl Instrument {
+    pub fn partials(self) -> Vec<Harmonic> {
+        let harmonics = |amplitudes: &[(u32, f32)]| {
+            amplitudes.iter().map(|&(number, amplitude)| Harmonic::nth(number, amplitude)).collect()
+        };
+        match self {
+            Instrument::Sine => vec![Harmonic::FUNDAMENTAL],
+            Instrument::Flute => harmonics(&[(1, 1.0), (2, 0.5), (3, 0.15), (4, 0.08), (5, 0.03)]),
+            // A closed pipe, odd harmonics only
+            Instrument::Clarinet => harmonics(&[(1, 1.0), (3, 0.6), (5, 0.35), (7, 0.2), (9, 0.1)]),
+            Instrument::Violin => harmonics(&[
+                (1, 1.0), (2, 0.6), (3, 0.5), (4, 0.35), (5, 0.3), (6, 0.2), (7, 0.15), (8, 0.1),
+            ]),
+            Instrument::Trumpet => harmonics(&[
+                (1, 1.0), (2, 0.9), (3, 0.8), (4, 0.65), (5, 0.5), (6, 0.35), (7, 0.25), (8, 0.15),
+            ]),
+            // 8', 4', 2 2/3', 2' and 1' drawbars
+            Instrument::Organ => harmonics(&[(1, 1.0), (2, 0.7), (3, 0.5), (4, 0.4), (8, 0.2)]),
+            // Hum, prime, minor third, fifth, nominal and the partials above
+            Instrument::Bell => [(0.5, 0.4), (1.0, 1.0), (1.2, 0.6), (1.5, 0.4), (2.0, 0.3), (2.5, 0.2), (2.67, 0.15)]
+                .into_iter()
+                .map(|(ratio, amplitude)| Harmonic { ratio, amplitude, phase: 0.0 })
+                .collect(),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Instrument::Sine => Instrument::Flute,
+            Instrument::Flute => Instrument::Clarinet,
+            Instrument::Clarinet => Instrument::Violin,
+            Instrument::Violin => Instrument::Trumpet,
+            Instrument::Trumpet => Instrument::Organ,
+            Instrument::Organ => Instrument::Bell,
+            Instrument::Bell => Instrument::Sine,
+        }
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Moves the selected emitter, or every emitter when none is selected, to the next preset.
+pub fn cycle_instrument(
+    selection_state: Res<SelectionState>,
+    mut emitter_query: Query<(Entity, &mut Instrument, &mut AudioEmitter)>,
+) {
+    for (entity, mut instrument, mut emitter) in emitter_query.iter_mut() {
+        if selection_state.selected_entity.is_some_and(|selected| selected != entity) { continue }
+        *instrument = instrument.next();
+        emitter.partials = instrument.partials();
+        info!("{:.2} Hz emitter plays as {}", emitter.frequency, *instrument);
+    }
+}
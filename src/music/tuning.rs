@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+use std::fmt;
+use std::path::Path;
+use crate::extras::components::AudioEmitter;
+use crate::music::note::{Note, Pitch, A4_MIDI};
+
+// A Scala file here is offered as one more tuning
+pub const USER_SCALE_FILE: &str = "tuning.scl";
+
+const JUST: [f64; 12] = [
+    1.0, 16.0 / 15.0, 9.0 / 8.0, 6.0 / 5.0, 5.0 / 4.0, 4.0 / 3.0,
+    45.0 / 32.0, 3.0 / 2.0, 8.0 / 5.0, 5.0 / 3.0, 9.0 / 5.0, 15.0 / 8.0,
+];
+const PYTHAGOREAN: [f64; 12] = [
+    1.0, 256.0 / 243.0, 9.0 / 8.0, 32.0 / 27.0, 81.0 / 64.0, 4.0 / 3.0,
+    729.0 / 512.0, 3.0 / 2.0, 128.0 / 81.0, 27.0 / 16.0, 16.0 / 9.0, 243.0 / 128.0,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TuningSystem {
+    Equal,
+    Just,
+    Pythagorean,
+    // Quarter-comma, with pure major thirds
+    Meantone,
+    Scala,
+}
+
+/// Scale degrees of a Scala .scl file as ratios above the tonic, the last one being the period.
+#[derive(Clone, Debug)]
+pub struct Scale {
+    pub description: String,
+    pub ratios: Vec<f64>,
+}
+
+impl Scale {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.starts_with('!'));
+        let description = lines.next().ok_or("missing description")?.to_string();
+        let count: usize = lines
+            .next()
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|count| count.parse().ok())
+            .ok_or("missing note count")?;
+        let ratios = lines
+            .filter(|line| !line.is_empty())
+            .take(count)
+            .map(|line| {
+                let value = line.split_whitespace().next().unwrap_or_default();
+                let ratio = if value.contains('.') {
+                    value.parse::<f64>().map(|cents| 2f64.powf(cents / 1200.0)).ok()
+                } else if let Some((numerator, denominator)) = value.split_once('/') {
+                    numerator.parse::<f64>().ok().zip(denominator.parse::<f64>().ok()).map(|(n, d)| n / d)
+                } else {
+                    value.parse::<f64>().ok()
+                };
+                ratio.filter(|ratio| *ratio > 0.0).ok_or(format!("bad pitch {value}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if ratios.len() != count || count == 0 {
+            return Err(format!("expected {count} pitches, found {}", ratios.len()));
+        }
+        Ok(Self { description, ratios })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Self::parse(&std::fs::read_to_string(path).map_err(|err| err.to_string())?)
+    }
+
+    /// Ratio of `steps` scale degrees above the tonic, repeating at the period.
+    fn ratio(&self, steps: i32) -> f64 {
+        let size = self.ratios.len() as i32;
+        let period = self.ratios[self.ratios.len() - 1];
+        let degree = steps.rem_euclid(size);
+        let base = if degree == 0 { 1.0 } else { self.ratios[degree as usize - 1] };
+        base * period.powi(steps.div_euclid(size))
+    }
+}
+
+/// How note names turn into frequencies. Every system agrees with 12-TET on the tonic;
+/// the other notes take the system's ratios above it.
+#[derive(Resource, Clone, Debug)]
+pub struct Tuning {
+    pub system: TuningSystem,
+    pub reference: f32, // A4, in Hz
+    pub tonic: i32,     // pitch class, 0 is C
+    pub scale: Option<Scale>,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            system: TuningSystem::Equal,
+            reference: 440.0,
+            tonic: 9, // A, so the starting chord is a pure minor triad in just intonation
+            scale: None,
+        }
+    }
+}
+
+impl Tuning {
+    pub fn frequency(&self, pitch: Pitch) -> f32 {
+        let tonic = (pitch.note.midi - self.tonic).div_euclid(12) * 12 + self.tonic;
+        let steps = pitch.note.midi - tonic;
+        let tonic_frequency = self.reference as f64 * 2f64.powf((tonic - A4_MIDI) as f64 / 12.0);
+        let ratio = match (self.system, &self.scale) {
+            (TuningSystem::Just, _) => JUST[steps as usize],
+            (TuningSystem::Pythagorean, _) => PYTHAGOREAN[steps as usize],
+            (TuningSystem::Meantone, _) => {
+                // Walk the chain of fifths from Eb to G# around the tonic, folded into one octave
+                let fifths = (-3..=8).find(|k: &i32| (k * 7).rem_euclid(12) == steps).unwrap_or(0);
+                let ratio = 5f64.powf(0.25).powi(fifths);
+                ratio / 2f64.powf(ratio.log2().floor())
+            }
+            (TuningSystem::Scala, Some(scale)) => scale.ratio(steps),
+            _ => 2f64.powf(steps as f64 / 12.0),
+        };
+        (tonic_frequency * ratio * 2f64.powf(pitch.cents as f64 / 1200.0)) as f32
+    }
+
+    fn next_system(&self) -> TuningSystem {
+        match self.system {
+            TuningSystem::Equal => TuningSystem::Just,
+            TuningSystem::Just => TuningSystem::Pythagorean,
+            TuningSystem::Pythagorean => TuningSystem::Meantone,
+            TuningSystem::Meantone if self.scale.is_some() => TuningSystem::Scala,
+            _ => TuningSystem::Equal,
+        }
+    }
+}
+
+impl fmt::Display for Tuning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tonic = Note { midi: self.tonic }.name();
+        match (self.system, &self.scale) {
+            (TuningSystem::Equal, _) => write!(f, "12-TET")?,
+            (TuningSystem::Just, _) => write!(f, "Just on {tonic}")?,
+            (TuningSystem::Pythagorean, _) => write!(f, "Pythagorean on {tonic}")?,
+            (TuningSystem::Meantone, _) => write!(f, "Meantone on {tonic}")?,
+            (TuningSystem::Scala, Some(scale)) => write!(f, "{} on {tonic}", scale.description)?,
+            (TuningSystem::Scala, None) => write!(f, "Scala")?,
+        }
+        write!(f, ", A4 = {:.1} Hz", self.reference)
+    }
+}
+
+/// The note an emitter plays, kept in its `AudioEmitter::frequency` under the current `Tuning`.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TunedNote {
+    pub pitch: Pitch,
+}
+
+pub fn load_user_scale(mut tuning: ResMut<Tuning>) {
+    let path = Path::new(USER_SCALE_FILE);
+    if !path.exists() { return }
+    match Scale::load(path) {
+        Ok(scale) => {
+            info!("Loaded {} ({} notes) from {}", scale.description, scale.ratios.len(), path.display());
+            tuning.scale = Some(scale);
+        }
+        Err(err) => warn!("Could not load {}: {err}", path.display()),
+    }
+}
+
+/// Dropping a .scl file on the window switches to it.
+pub fn load_dropped_scale(
+    mut drops: EventReader<FileDragAndDrop>,
+    mut tuning: ResMut<Tuning>,
+) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else { continue };
+        if !path_buf.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("scl")) {
+            continue;
+        }
+        match Scale::load(path_buf) {
+            Ok(scale) => {
+                tuning.scale = Some(scale);
+                tuning.system = TuningSystem::Scala;
+            }
+            Err(err) => warn!("Could not read {}: {err}", path_buf.display()),
+        }
+    }
+}
+
+pub fn tuning_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut tuning: ResMut<Tuning>,
+) {
+    if keyboard.just_pressed(KeyCode::KeyY) {
+        tuning.system = tuning.next_system();
+    }
+    if keyboard.just_pressed(KeyCode::Semicolon) {
+        tuning.reference = (tuning.reference - 1.0).max(400.0);
+    }
+    if keyboard.just_pressed(KeyCode::Quote) {
+        tuning.reference = (tuning.reference + 1.0).min(480.0);
+    }
+}
+
+pub fn retune_emitters(
+    tuning: Res<Tuning>,
+    mut emitter_query: Query<(Ref<TunedNote>, &mut AudioEmitter)>,
+) {
+    if tuning.is_changed() {
+        info!("Tuning: {}", *tuning);
+    }
+    for (note, mut emitter) in emitter_query.iter_mut() {
+        if !tuning.is_changed() && !note.is_changed() { continue }
+        emitter.frequency = tuning.frequency(note.pitch);
+        info!("{} plays {:.2} Hz", note.pitch, emitter.frequency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frequency(tuning: &Tuning, pitch: &str) -> f32 {
+        tuning.frequency(pitch.parse().unwrap())
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3 * b
+    }
+
+    #[test]
+    fn scala_files_parse_ratios_and_cents() {
+        let text = "! a comment\n Half a meantone\n 3\n!\n 193.157 ! cents\n 5/4\n 2\n";
+        let scale = Scale::parse(text).unwrap();
+        assert_eq!(scale.description, "Half a meantone");
+        assert_eq!(scale.ratios.len(), 3);
+        assert!((scale.ratios[0] - 2f64.powf(193.157 / 1200.0)).abs() < 1e-9);
+        assert_eq!(scale.ratios[1], 1.25);
+        assert_eq!(scale.ratios[2], 2.0);
+        assert_eq!(scale.ratio(4), 2.0 * 2f64.powf(193.157 / 1200.0));
+    }
+
+    #[test]
+    fn malformed_scala_files_are_rejected() {
+        assert!(Scale::parse("").is_err());
+        assert!(Scale::parse("Too few\n3\n5/4\n2\n").is_err());
+        assert!(Scale::parse("Bad pitch\n1\nfive\n").is_err());
+        assert!(Scale::parse("Empty\n0\n").is_err());
+    }
+
+    #[test]
+    fn tuning_systems_agree_on_the_tonic() {
+        let mut tuning = Tuning::default();
+        for system in [TuningSystem::Equal, TuningSystem::Just, TuningSystem::Pythagorean, TuningSystem::Meantone] {
+            tuning.system = system;
+            assert!(close(frequency(&tuning, "A4"), 440.0), "{system:?}");
+            assert!(close(frequency(&tuning, "A3"), 220.0), "{system:?}");
+        }
+    }
+
+    #[test]
+    fn tuning_systems_take_their_own_ratios() {
+        let mut tuning = Tuning::default();
+        assert!(close(frequency(&tuning, "C5"), 523.2511));
+        assert!(close(frequency(&tuning, "A4+100c"), frequency(&tuning, "A#4")));
+        tuning.system = TuningSystem::Just;
+        assert!(close(frequency(&tuning, "C5"), 528.0));
+        assert!(close(frequency(&tuning, "E5"), 660.0));
+        tuning.system = TuningSystem::Pythagorean;
+        assert!(close(frequency(&tuning, "C5"), 440.0 * 32.0 / 27.0));
+        tuning.system = TuningSystem::Meantone;
+        assert!(close(frequency(&tuning, "C#5"), 550.0)); // a pure major third
+        tuning.reference = 432.0;
+        tuning.system = TuningSystem::Equal;
+        assert!(close(frequency(&tuning, "A5"), 864.0));
+    }
+
+    #[test]
+    fn scala_tunings_repeat_at_the_period() {
+        let scale = Scale::parse("Fifths\n2\n3/2\n2\n").unwrap();
+        let tuning = Tuning { system: TuningSystem::Scala, scale: Some(scale), ..default() };
+        assert!(close(frequency(&tuning, "A#4"), 660.0));
+        assert!(close(frequency(&tuning, "B4"), 880.0));
+        assert!(close(frequency(&tuning, "C5"), 1320.0));
+    }
+}
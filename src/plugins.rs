@@ -0,0 +1,337 @@
+use bevy::prelude::*;
+use bevy::input::common_conditions::input_just_pressed;
+use bevy::app::AppExit;
+use bevy::input::{InputPlugin, InputSystem};
+use bevy::log::LogPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::PrimaryWindow;
+use std::path::{Path, PathBuf};
+use crate::extras::resources::*;
+use crate::acoustics::material::*;
+use crate::acoustics::medium::*;
+use crate::acoustics::modes::*;
+use crate::acoustics::propagation::*;
+use crate::acoustics::raytrace::*;
+use crate::acoustics::room::*;
+use crate::audio::engine::*;
+use crate::audio::harmony::*;
+use crate::audio::impulse::*;
+use crate::audio::loudness::*;
+use crate::audio::microphone::*;
+use crate::audio::offline::*;
+use crate::audio::pitch::*;
+use crate::audio::rhythm::*;
+use crate::camera::bookmarks::*;
+use crate::camera::fly::*;
+use crate::camera::focus::*;
+use crate::camera::orbit::*;
+use crate::camera::selection::*;
+use crate::input::map::*;
+use crate::music::roughness::*;
+use crate::music::timbre::*;
+use crate::music::tuning::*;
+use crate::scene::layout::*;
+use crate::settings::*;
+use crate::simulation::*;
+use crate::ui::bindings::*;
+use crate::ui::loudness::*;
+use crate::ui::microphones::*;
+use crate::ui::panel::*;
+use crate::ui::readouts::*;
+use crate::visuals::partials::*;
+use crate::visuals::rhythm::*;
+
+/// Reads `input.toml` and turns raw input into `ActionState` before `Update`. The other
+/// plugins add it themselves if it is missing.
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .configure_sets(Update, Shortcuts.run_if(not_rebinding))
+            .add_systems(Startup, load_user_input_map)
+            .add_systems(PreUpdate, update_actions.after(InputSystem));
+    }
+}
+
+// Read when the plugins are built, the room and the scene are made from it
+fn add_scene_layout(app: &mut App) {
+    if !app.world.contains_resource::<SceneLayout>() {
+        app.insert_resource(load_scene_layout(Path::new(SCENE_FILE)));
+    }
+}
+
+fn add_input_map(app: &mut App) {
+    if !app.is_plugin_added::<InputMapPlugin>() {
+        app.add_plugins(InputMapPlugin);
+    }
+}
+
+/// Simulation time, propagation, the audio engine and its output, and every analysis of
+/// what the listeners hear. Insert an `AudioEngine`, `SimulationTime` or `SceneLayout`
+/// first to start from your own.
+pub struct AudioSimPlugin;
+
+impl Plugin for AudioSimPlugin {
+    fn build(&self, app: &mut App) {
+        add_input_map(app);
+        add_scene_layout(app);
+        let engine = app.world.get_resource::<AudioEngine>().cloned().unwrap_or_default();
+        // The room's faces are looked up by name, so the user's materials have to be in first
+        if !app.world.contains_resource::<MaterialLibrary>() {
+            app.insert_resource(load_user_materials());
+        }
+        app.init_resource::<SimulationTime>()
+            .init_resource::<SelectionState>()
+            .init_resource::<AudioSettings>()
+            .init_resource::<Medium>()
+            .init_resource::<Room>()
+            .init_resource::<PropagationPaths>()
+            .init_resource::<RayTracing>()
+            .init_resource::<ReverbEstimate>()
+            .init_resource::<LoadedImpulseResponse>()
+            .init_resource::<Monitor>()
+            .init_resource::<ListenerMeters>()
+            .init_resource::<DetectedPitch>()
+            .init_resource::<HarmonyAnalysis>()
+            .init_resource::<Tuning>()
+            .init_resource::<PairAnalysis>()
+            .init_resource::<RhythmTracker>()
+            .init_resource::<Loudness>()
+            .add_event::<OnsetEvent>()
+            .add_event::<BeatEvent>()
+            .insert_resource(engine)
+            .insert_resource(Time::<Fixed>::from_duration(tick_duration()))
+            .add_systems(Startup, (open_audio_output, load_user_scale))
+            .add_systems(FixedUpdate, (update_sim, rotate_sphere.after(update_sim)))
+            .add_systems(Update, (
+                    room_controls.in_set(Shortcuts),
+                    cycle_ground_material.in_set(Shortcuts),
+                    medium_controls.in_set(Shortcuts),
+                    update_propagation
+                        .after(orbit_camera)
+                        .after(cycle_ground_material)
+                        .after(medium_controls),
+                    report_ground_reflection.after(update_propagation),
+                    trace_reverb.run_if(input_just_pressed(KeyCode::KeyT)).in_set(Shortcuts),
+                    export_impulse_response
+                        .run_if(input_just_pressed(KeyCode::KeyI))
+                        .after(update_propagation)
+                        .in_set(Shortcuts),
+                    load_dropped_impulse_response,
+                    unload_impulse_response.run_if(input_just_pressed(KeyCode::KeyL)).in_set(Shortcuts),
+                    apply_reverb
+                        .after(trace_reverb)
+                        .after(load_dropped_impulse_response)
+                        .after(unload_impulse_response)
+                        .in_set(Shortcuts),
+                    remove_microphone
+                        .run_if(action_just_pressed(Action::Delete))
+                        .before(update_propagation),
+                    sync_monitor.after(solo_microphone).after(remove_microphone),
+                    update_meters.after(place_microphone).after(remove_microphone),
+                    save_recordings.run_if(input_just_pressed(KeyCode::F9)).in_set(Shortcuts),
+            ))
+            .add_systems(Update, (
+                    detect_listener_pitch,
+                    tuning_controls.in_set(Shortcuts),
+                    load_dropped_scale,
+                    retune_emitters.after(tuning_controls).after(load_dropped_scale),
+                    recognize_harmony.after(retune_emitters),
+                    analyze_emitter_pairs.after(retune_emitters),
+                    track_rhythm.after(sync_monitor),
+                    reset_loudness.run_if(input_just_pressed(KeyCode::Backspace)).in_set(Shortcuts),
+                    update_loudness.after(reset_loudness),
+                    cycle_instrument.run_if(input_just_pressed(KeyCode::KeyH)).in_set(Shortcuts),
+            ));
+    }
+}
+
+/// The orbit and fly cameras, picking, focus, bookmarks and fly-throughs. Drives the
+/// camera spawned with `OrbitCamera` and `FlyCamera`.
+pub struct OrbitCameraPlugin;
+
+impl Plugin for OrbitCameraPlugin {
+    fn build(&self, app: &mut App) {
+        add_input_map(app);
+        add_scene_layout(app);
+        app.init_resource::<CameraController>()
+            .init_resource::<CameraFocus>()
+            .init_resource::<CameraMode>()
+            .init_resource::<CameraPlayback>()
+            .add_systems(Update, (
+                    orbit_camera.run_if(resource_equals(CameraMode::Orbit)),
+                    object_selection,
+                    focus_on_selection.after(object_selection).in_set(Shortcuts),
+                    toggle_focus_tracking.run_if(input_just_pressed(KeyCode::KeyK)).in_set(Shortcuts),
+                    follow_focus
+                        .run_if(resource_equals(CameraMode::Orbit))
+                        .after(focus_on_selection)
+                        .after(toggle_focus_tracking)
+                        .before(orbit_camera),
+                    toggle_camera_mode.run_if(input_just_pressed(KeyCode::KeyC)).before(orbit_camera).in_set(Shortcuts),
+                    fly_camera.run_if(resource_equals(CameraMode::Fly)).after(toggle_camera_mode).in_set(Shortcuts),
+                    bookmark_keys.after(toggle_camera_mode).in_set(Shortcuts),
+                    toggle_tour.run_if(input_just_pressed(KeyCode::KeyP)).after(toggle_camera_mode).in_set(Shortcuts),
+                    play_camera
+                        .run_if(resource_equals(CameraMode::Orbit))
+                        .after(bookmark_keys)
+                        .after(toggle_tour)
+                        .after(follow_focus)
+                        .before(orbit_camera),
+            ));
+    }
+}
+
+/// The side panel: speed, microphones, readouts, loudness and the binding editor.
+pub struct ControlPanelPlugin;
+
+impl Plugin for ControlPanelPlugin {
+    fn build(&self, app: &mut App) {
+        add_input_map(app);
+        app.add_systems(Startup, setup_ui)
+            .add_systems(Update, (
+                    update_collapse,
+                    content_visibility,
+                    toggle_speed_section,
+                    speed_input,
+                    update_speed_display,
+                    toggle_pause.run_if(action_just_pressed(Action::Pause)),
+                    place_microphone.before(update_propagation).in_set(Shortcuts),
+                    solo_microphone,
+                    rebuild_microphone_list.after(update_meters),
+                    update_microphone_rows.after(update_meters),
+            ))
+            .add_systems(Update, (
+                    update_tuner_text.after(detect_listener_pitch),
+                    update_chord_text.after(recognize_harmony),
+                    update_tuning_text.after(retune_emitters),
+                    update_rhythm_text.after(track_rhythm),
+                    rebuild_loudness_list.after(update_loudness),
+                    update_loudness_rows.after(rebuild_loudness_list),
+                    spawn_binding_rows,
+                    capture_binding.after(Shortcuts).before(start_rebind),
+                    start_rebind,
+                    update_binding_rows.after(start_rebind).after(capture_binding),
+            ));
+    }
+}
+
+/// Reflection paths, the room mode slice, microphones, beat lines, onset flashes and
+/// partial satellites drawn over the scene.
+pub struct VisualizationPlugin;
+
+impl Plugin for VisualizationPlugin {
+    fn build(&self, app: &mut App) {
+        add_input_map(app);
+        app.init_resource::<ModeView>()
+            .add_systems(Startup, spawn_mode_slice)
+            .add_systems(Update, (
+                    draw_reflections.after(update_propagation),
+                    update_room_modes.after(room_controls).after(medium_controls),
+                    mode_controls.after(update_room_modes).in_set(Shortcuts),
+                    draw_mode_slice.after(mode_controls),
+                    draw_microphones.after(update_meters),
+                    toggle_pair_lines.run_if(input_just_pressed(KeyCode::KeyB)).in_set(Shortcuts),
+                    draw_emitter_pairs.after(analyze_emitter_pairs).after(toggle_pair_lines),
+                    flash_on_onsets.after(track_rhythm),
+                    bump_camera_on_beats.after(track_rhythm),
+                    sync_satellites.after(cycle_instrument),
+                    animate_satellites.after(sync_satellites),
+            ));
+    }
+}
+
+/// Starts from the `UserSettings` resource, or the saved settings if there is none, and
+/// writes them back on exit. Add it after `DefaultPlugins`, it sizes and places the
+/// primary window before it opens. A `SimulationTime`, `CameraController` or
+/// `AudioSettings` inserted first is used for the run instead of the saved one. The
+/// speed and controller are only saved if they change; the audio settings are never
+/// changed in the app, so the saved ones are kept.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = match app.world.remove_resource::<UserSettings>() {
+            Some(settings) => settings,
+            None => load_user_settings(),
+        };
+        let mut windows = app.world.query_filtered::<&mut Window, With<PrimaryWindow>>();
+        if let Ok(mut window) = windows.get_single_mut(&mut app.world) {
+            settings.window.apply(&mut window);
+        }
+        if !app.world.contains_resource::<SimulationTime>() {
+            app.insert_resource(SimulationTime { speed_multiplier: settings.speed_multiplier, ..default() });
+        }
+        if !app.world.contains_resource::<CameraController>() {
+            app.insert_resource(settings.camera.controller());
+        }
+        if !app.world.contains_resource::<AudioSettings>() {
+            app.insert_resource(settings.audio.clone());
+        }
+        let launch = LaunchSettings {
+            speed_multiplier: app.world.resource::<SimulationTime>().speed_multiplier,
+            controller: app.world.resource::<CameraController>().clone(),
+        };
+        app.insert_resource(launch)
+            .insert_resource(settings)
+            .add_systems(PostStartup, restore_user_settings)
+            .add_systems(Update, track_window_geometry)
+            .add_systems(Last, save_user_settings);
+    }
+}
+
+/// Runs without a window, renderer or audio device for `ticks` fixed steps, one a frame,
+/// then exits, writing the mix to `render` if set. Add it instead of `DefaultPlugins`,
+/// together with `AudioSimPlugin`.
+pub struct HeadlessPlugin {
+    pub ticks: u32,
+    pub render: Option<PathBuf>,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        let offline = match self.render {
+            Some(_) => OfflineAudio::recording(),
+            None => OfflineAudio::default(),
+        };
+        app.add_plugins((MinimalPlugins, LogPlugin::default(), TransformPlugin, HierarchyPlugin, InputPlugin))
+            .add_event::<FileDragAndDrop>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(tick_duration()))
+            .insert_resource(HeadlessRun { ticks: self.ticks, render: self.render.clone() })
+            .insert_resource(offline)
+            .add_systems(FixedUpdate, render_offline_audio.after(update_sim))
+            .add_systems(Last, stop_headless_run);
+    }
+}
+
+#[derive(Resource)]
+struct HeadlessRun {
+    ticks: u32,
+    render: Option<PathBuf>,
+}
+
+fn stop_headless_run(
+    run: Res<HeadlessRun>,
+    sim_time: Res<SimulationTime>,
+    mut offline: ResMut<OfflineAudio>,
+    loudness: Res<Loudness>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if sim_time.tick < run.ticks as u64 { return }
+    let integrated = loudness.master.integrated.map_or("-".to_string(), |lufs| format!("{lufs:.1} LUFS"));
+    info!(
+        "Ran {} ticks, {:.4} s of simulation and {} samples of audio, integrated loudness {integrated}",
+        sim_time.tick, sim_time.elapsed, offline.rendered(),
+    );
+    // The recording is left alone unless it is this run's to write
+    if let Some(path) = &run.render {
+        let samples = offline.take_recording().unwrap_or_default();
+        match write_wav(path, &samples) {
+            Ok(()) => info!("Rendered {:.2} s of the mix to {}", samples.len() as f32 / SAMPLE_RATE as f32, path.display()),
+            Err(err) => error!("Could not write {}: {err}", path.display()),
+        }
+    }
+    exit.send(AppExit);
+}
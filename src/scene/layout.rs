@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::camera::bookmarks::{Bookmark, FlyPath};
+
+pub const SCENE_FILE: &str = "scene.toml";
+
+/// The shoebox around the scene, its surfaces named from the `MaterialLibrary`. The
+/// floor is the ground plane.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomLayout {
+    pub size: [f32; 3], // interior width, height, depth
+    pub ground: String,
+    pub ceiling: String,
+    pub walls: [String; 4], // -x, +x, -z, +z
+}
+
+impl Default for RoomLayout {
+    fn default() -> Self {
+        Self {
+            size: [24.0, 10.0, 24.0],
+            ground: "concrete".to_string(),
+            ceiling: "acoustic tile".to_string(),
+            walls: ["brick", "brick", "curtains", "brick"].map(String::from),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EmitterLayout {
+    pub note: String, // like `A4` or `C5+12c`
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub phase: f32,
+}
+
+/// A free-standing reflector, upright and facing +z before turning by `yaw` degrees.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WallLayout {
+    pub center: [f32; 3],
+    pub size: [f32; 2], // width, height
+    #[serde(default)]
+    pub yaw: f32,
+    pub material: String,
+}
+
+/// A microphone at ear height over `position` on the floor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MicrophoneLayout {
+    pub name: String,
+    pub position: [f32; 2],
+}
+
+/// Everything about the scene that is kept between runs, read from and written back to
+/// `path` as TOML. Whatever the file leaves out is the demo scene's.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneLayout {
+    #[serde(skip)]
+    pub path: PathBuf,
+    // Set when the file at `path` could not be read, so saving cannot overwrite it
+    #[serde(skip)]
+    pub read_only: bool,
+    pub room: RoomLayout,
+    #[serde(rename = "emitter")]
+    pub emitters: Vec<EmitterLayout>,
+    #[serde(rename = "wall")]
+    pub walls: Vec<WallLayout>,
+    #[serde(rename = "microphone")]
+    pub microphones: Vec<MicrophoneLayout>,
+    #[serde(rename = "bookmark", skip_serializing_if = "Vec::is_empty")]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(rename = "path", skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<FlyPath>,
+}
+
+impl Default for SceneLayout {
+    fn default() -> Self {
+        // An A minor chord around the rotating sphere, outside its path
+        let chord = [("A4", [1.0, 0.0, 0.0]), ("C5", [0.0, 1.0, 0.0]), ("E5", [0.0, 0.0, 1.0])];
+        let emitters = chord
+            .iter()
+            .enumerate()
+            .map(|(i, (note, color))| {
+                let angle = i as f32 / chord.len() as f32 * std::f32::consts::TAU;
+                EmitterLayout {
+                    note: note.to_string(),
+                    position: [3.5 * angle.cos(), 0.5, 3.5 * angle.sin()],
+                    color: *color,
+                    phase: angle,
+                }
+            })
+            .collect();
+        Self {
+            path: PathBuf::from(SCENE_FILE),
+            read_only: false,
+            room: RoomLayout::default(),
+            emitters,
+            // A partition to walk behind
+            walls: vec![WallLayout {
+                center: [0.0, 1.5, -7.0],
+                size: [6.0, 3.0],
+                yaw: 0.0,
+                material: "wood".to_string(),
+            }],
+            // Two seats to compare against the camera
+            microphones: vec![
+                MicrophoneLayout { name: "Mic 1".to_string(), position: [0.0, 6.0] },
+                MicrophoneLayout { name: "Mic 2".to_string(), position: [-8.0, 9.0] },
+            ],
+            bookmarks: Vec::new(),
+            paths: Vec::new(),
+        }
+    }
+}
+
+impl SceneLayout {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let layout: Self = toml::from_str(&text).map_err(|err| err.to_string())?;
+        Ok(Self { path: path.to_path_buf(), ..layout })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err("it could not be read at launch, so it is left as it is".to_string());
+        }
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, text).map_err(|err| err.to_string())
+    }
+}
+
+/// Reads the layout at `path`, or starts from the demo scene if there is no file there
+/// yet; it is written there once something is saved. A file that cannot be read is
+/// never saved over.
+pub fn load_scene_layout(path: &Path) -> SceneLayout {
+    if !path.exists() {
+        return SceneLayout { path: path.to_path_buf(), ..default() };
+    }
+    match SceneLayout::load(path) {
+        Ok(layout) => {
+            info!(
+                "Loaded {} emitters, {} bookmarks and {} paths from {}",
+                layout.emitters.len(), layout.bookmarks.len(), layout.paths.len(), path.display(),
+            );
+            layout
+        }
+        Err(err) => {
+            warn!("Could not load {}, using the demo scene and not saving to it: {err}", path.display());
+            SceneLayout { path: path.to_path_buf(), read_only: true, ..default() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_file_that_fails_to_load_is_never_saved_over() {
+        let path = std::env::temp_dir().join(format!("scene-{}.toml", std::process::id()));
+        std::fs::write(&path, "room = 3").unwrap();
+        let layout = load_scene_layout(&path);
+        assert!(layout.read_only);
+        assert_eq!(layout.emitters.len(), SceneLayout::default().emitters.len());
+        assert!(layout.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "room = 3");
+
+        std::fs::remove_file(&path).unwrap();
+        let layout = load_scene_layout(&path);
+        assert!(!layout.read_only);
+        layout.save().unwrap();
+        let saved = SceneLayout::load(&path).unwrap();
+        assert_eq!(saved.walls.len(), layout.walls.len());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
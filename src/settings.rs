@@ -0,0 +1,237 @@
+use bevy::prelude::*;
+use bevy::app::AppExit;
+use bevy::window::{PrimaryWindow, WindowMode, WindowPosition, WindowResolution};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::audio::engine::AudioSettings;
+use crate::camera::orbit::OrbitCamera;
+use crate::extras::components::UiPanel;
+use crate::extras::resources::{CameraController, SimulationTime};
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Where the app keeps its settings: the platform's config directory, or the working
+/// directory if there is none.
+pub fn settings_path() -> PathBuf {
+    #[cfg(target_os = "windows")]
+    let config = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let config = std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    match config {
+        Some(dir) => dir.join("fundamentals").join(SETTINGS_FILE),
+        None => PathBuf::from(SETTINGS_FILE),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowSettings {
+    pub width: f32, // in logical pixels, as last seen windowed
+    pub height: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<[i32; 2]>, // left to the system if unset
+    pub fullscreen: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        let resolution = WindowResolution::default();
+        Self { width: resolution.width(), height: resolution.height(), position: None, fullscreen: false }
+    }
+}
+
+impl WindowSettings {
+    fn of(window: &Window, previous: &WindowSettings) -> Self {
+        let fullscreen = window.mode != WindowMode::Windowed;
+        // A fullscreen window is the size of the screen, keep the size to come back to
+        let (width, height) = match fullscreen {
+            true => (previous.width, previous.height),
+            false => (window.resolution.width(), window.resolution.height()),
+        };
+        let position = match window.position {
+            WindowPosition::At(position) => Some(position.to_array()),
+            _ => previous.position,
+        };
+        Self { width, height, position, fullscreen }
+    }
+
+    pub fn apply(&self, window: &mut Window) {
+        window.resolution = WindowResolution::new(self.width, self.height);
+        if let Some([x, y]) = self.position {
+            window.position = WindowPosition::At(IVec2::new(x, y));
+        }
+        window.mode = if self.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed };
+    }
+}
+
+/// The orbit rig and how fast it answers the mouse.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub focus: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub radius: f32,
+    pub sensitivity: f32,
+    pub zoom_speed: f32,
+    pub pan_sensitivity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self::new(&OrbitCamera::default(), &CameraController::default())
+    }
+}
+
+impl CameraSettings {
+    fn new(orbit: &OrbitCamera, controller: &CameraController) -> Self {
+        Self {
+            focus: orbit.focus.to_array(),
+            yaw: orbit.yaw,
+            pitch: orbit.pitch,
+            radius: orbit.radius,
+            sensitivity: controller.sensitivity,
+            zoom_speed: controller.zoom_speed,
+            pan_sensitivity: controller.pan_sensitivity,
+        }
+    }
+
+    pub fn orbit(&self) -> OrbitCamera {
+        OrbitCamera { focus: Vec3::from(self.focus), yaw: self.yaw, pitch: self.pitch, radius: self.radius }
+    }
+
+    pub fn controller(&self) -> CameraController {
+        CameraController {
+            sensitivity: self.sensitivity,
+            zoom_speed: self.zoom_speed,
+            pan_sensitivity: self.pan_sensitivity,
+        }
+    }
+}
+
+/// How the app was left, read on launch and written back to `path` as TOML on exit.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    #[serde(skip)]
+    pub path: PathBuf,
+    pub speed_multiplier: f32,
+    pub panel_collapsed: bool,
+    pub window: WindowSettings,
+    pub camera: CameraSettings,
+    pub audio: AudioSettings,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            speed_multiplier: SimulationTime::default().speed_multiplier,
+            panel_collapsed: false,
+            window: WindowSettings::default(),
+            camera: CameraSettings::default(),
+            audio: AudioSettings::default(),
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let settings: Self = toml::from_str(&text).map_err(|err| err.to_string())?;
+        Ok(Self { path: path.to_path_buf(), ..settings })
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        std::fs::write(&self.path, text).map_err(|err| err.to_string())
+    }
+}
+
+/// The speed and camera controller the run started with, after any the app set itself.
+/// Only what changes from them is saved, so options for a single run are not kept.
+#[derive(Resource)]
+pub struct LaunchSettings {
+    pub speed_multiplier: f32,
+    pub controller: CameraController,
+}
+
+/// Reads the settings from `settings_path()`, or starts from the defaults if there are none.
+pub fn load_user_settings() -> UserSettings {
+    let path = settings_path();
+    if !path.exists() {
+        return UserSettings { path, ..default() };
+    }
+    match UserSettings::load(&path) {
+        Ok(settings) => {
+            info!("Loaded settings from {}", path.display());
+            settings
+        }
+        Err(err) => {
+            warn!("Could not load {}: {err}", path.display());
+            UserSettings { path, ..default() }
+        }
+    }
+}
+
+/// Keeps the window geometry up to date, the window is gone by the time the app exits.
+/// The geometry it opened with is left out, like the other launch settings.
+pub fn track_window_geometry(
+    window_query: Query<&Window, (With<PrimaryWindow>, Changed<Window>)>,
+    mut launch: Local<Option<WindowSettings>>,
+    mut settings: ResMut<UserSettings>,
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let geometry = WindowSettings::of(window, &settings.window);
+    let launch = launch.get_or_insert_with(|| geometry.clone());
+    if geometry != *launch {
+        settings.window = geometry;
+    }
+}
+
+/// Puts the camera rig and panel back the way they were left, once both are spawned.
+pub fn restore_user_settings(
+    settings: Res<UserSettings>,
+    mut orbit_query: Query<&mut OrbitCamera>,
+    mut panel_query: Query<&mut UiPanel>,
+) {
+    for mut orbit in orbit_query.iter_mut() {
+        *orbit = settings.camera.orbit();
+    }
+    for mut panel in panel_query.iter_mut() {
+        panel.collapsed = settings.panel_collapsed;
+    }
+}
+
+pub fn save_user_settings(
+    mut exits: EventReader<AppExit>,
+    mut settings: ResMut<UserSettings>,
+    (controller, sim_time, launch): (Res<CameraController>, Res<SimulationTime>, Res<LaunchSettings>),
+    orbit_query: Query<&OrbitCamera>,
+    panel_query: Query<&UiPanel>,
+) {
+    if exits.read().last().is_none() { return }
+    if let Ok(orbit) = orbit_query.get_single() {
+        let controller = if *controller != launch.controller { controller.clone() } else { settings.camera.controller() };
+        settings.camera = CameraSettings::new(orbit, &controller);
+    }
+    // Paused is not a speed to come back to
+    if sim_time.speed_multiplier > 0.0 && sim_time.speed_multiplier != launch.speed_multiplier {
+        settings.speed_multiplier = sim_time.speed_multiplier;
+    }
+    if let Ok(panel) = panel_query.get_single() {
+        settings.panel_collapsed = panel.collapsed;
+    }
+    match settings.save() {
+        Ok(()) => info!("Saved settings to {}", settings.path.display()),
+        Err(err) => warn!("Could not write {}: {err}", settings.path.display()),
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use std::time::Duration;
+use crate::audio::engine::SAMPLE_RATE;
+use crate::extras::components::AudioEmitter;
+use crate::extras::resources::SimulationTime;
+
+// One fixed step is exactly this many samples of audio, two mixer blocks
+pub const TICK_SAMPLES: u32 = 800;
+pub const TICK_SECONDS: f32 = TICK_SAMPLES as f32 / SAMPLE_RATE as f32;
+// Quarter of a turn round the origin, and two radians of spin, per second at the default speed
+const SPHERE_ORBIT_RATE: f32 = 50.0 * std::f32::consts::PI; // in radians per simulation second
+const SPHERE_SPIN_RATE: f32 = 200.0; // in radians per simulation second
+const SPHERE_ORBIT_RADIUS: f32 = 2.0;
+
+/// Wall time between fixed steps, for `Time<Fixed>` and anything stepping time by hand.
+pub fn tick_duration() -> Duration {
+    Duration::from_nanos(1_000_000_000 * TICK_SAMPLES as u64 / SAMPLE_RATE as u64)
+}
+
+/// Circles the origin, giving the reflections and occlusion something to follow.
+#[derive(Component)]
+pub struct RotatingSphere;
+
+/// Advances the clock by one fixed step and pulses the emitters. Runs in `FixedUpdate`, so
+/// the simulation only depends on how many steps ran, never on the frame rate.
+pub fn update_sim(
+    mut sim_time: ResMut<SimulationTime>,
+    mut query: Query<(&mut Transform, &AudioEmitter)>,
+) {
+    sim_time.tick += 1;
+    sim_time.elapsed += TICK_SECONDS * sim_time.speed_multiplier;
+
+    for (mut transform, emitter) in query.iter_mut() {
+        let wave = ((emitter.frequency * sim_time.elapsed * std::f32::consts::TAU) + emitter.phase).sin();
+        let scale = 1.0 + wave * emitter.amplitude;
+        transform.scale = Vec3::splat(scale);
+    }
+}
+
+pub fn rotate_sphere(
+    sim_time: Res<SimulationTime>,
+    mut query: Query<&mut Transform, With<RotatingSphere>>,
+) {
+    let angle = sim_time.elapsed * SPHERE_ORBIT_RATE;
+    for mut transform in &mut query {
+        transform.translation.x = SPHERE_ORBIT_RADIUS * angle.cos();
+        transform.translation.z = SPHERE_ORBIT_RADIUS * angle.sin();
+        transform.rotation = Quat::from_rotation_y(sim_time.elapsed * SPHERE_SPIN_RATE);
+    }
+}
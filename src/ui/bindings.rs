@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy::input::mouse::MouseWheel;
+use crate::input::map::{nameable_key, Action, Binding, InputMap, Side};
+
+// A stick has to be pushed this far to be taken as the new binding
+const STICK_CAPTURE: f32 = 0.6;
+
+#[derive(Component)]
+pub struct BindingList;
+
+/// Clicking a row waits for the next input and binds it to the row's action.
+#[derive(Component)]
+pub struct BindingRow {
+    pub action: Action,
+}
+
+#[derive(Component)]
+pub struct BindingText {
+    pub action: Action,
+}
+
+pub fn spawn_binding_rows(
+    mut commands: Commands,
+    list_query: Query<Entity, Added<BindingList>>,
+) {
+    for list in list_query.iter() {
+        commands.entity(list).with_children(|parent| {
+            for action in Action::ALL {
+                parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            padding: UiRect::all(Val::Px(4.0)),
+                            margin: UiRect::vertical(Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: Color::GRAY.into(),
+                        ..default()
+                    },
+                    BindingRow { action },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            action.to_string(),
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                                ..default()
+                            }
+                        ),
+                        BindingText { action },
+                    ));
+                });
+            }
+        });
+    }
+}
+
+pub fn start_rebind(
+    interaction_query: Query<(&Interaction, &BindingRow), Changed<Interaction>>,
+    mut input_map: ResMut<InputMap>,
+) {
+    for (interaction, row) in interaction_query.iter() {
+        if let Interaction::Pressed = interaction {
+            input_map.listening = Some(row.action);
+        }
+    }
+}
+
+/// Takes the first key, button, scroll or stick push while listening; Escape cancels.
+pub fn capture_binding(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut ev_scroll: EventReader<MouseWheel>,
+    pad_buttons: Res<ButtonInput<GamepadButton>>,
+    (gamepads, pad_axes): (Res<Gamepads>, Res<Axis<GamepadAxis>>),
+    mut input_map: ResMut<InputMap>,
+) {
+    let scroll: f32 = ev_scroll.read().map(|ev| ev.y).sum();
+    let Some(action) = input_map.listening else { return };
+    if keyboard.just_pressed(KeyCode::Escape) {
+        input_map.listening = None;
+        return;
+    }
+
+    let stick = gamepads.iter().find_map(|gamepad| {
+        let axis = |kind| pad_axes.get(GamepadAxis::new(gamepad, kind)).unwrap_or_default();
+        let left = Vec2::new(axis(GamepadAxisType::LeftStickX), axis(GamepadAxisType::LeftStickY));
+        let right = Vec2::new(axis(GamepadAxisType::RightStickX), axis(GamepadAxisType::RightStickY));
+        if left.length() > STICK_CAPTURE {
+            Some(Binding::Stick(Side::Left))
+        } else if right.length() > STICK_CAPTURE {
+            Some(Binding::Stick(Side::Right))
+        } else {
+            None
+        }
+    });
+    let binding = keyboard
+        .get_just_pressed()
+        .find(|key| nameable_key(**key))
+        .map(|key| Binding::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|button| Binding::Mouse(*button)))
+        .or_else(|| pad_buttons.get_just_pressed().next().map(|button| Binding::Pad(button.button_type)))
+        .or(if scroll > 0.0 { Some(Binding::ScrollUp) } else if scroll < 0.0 { Some(Binding::ScrollDown) } else { None })
+        .or(stick);
+    let Some(binding) = binding else { return };
+
+    input_map.rebind(action, binding);
+    input_map.listening = None;
+    info!("{action} bound to {binding}");
+    match input_map.save() {
+        Ok(()) => info!("Saved bindings to {}", input_map.path.display()),
+        Err(err) => warn!("Could not write {}: {err}", input_map.path.display()),
+    }
+}
+
+pub fn update_binding_rows(
+    input_map: Res<InputMap>,
+    mut row_query: Query<(&BindingRow, &mut BackgroundColor)>,
+    mut text_query: Query<(&BindingText, &mut Text)>,
+    added: Query<(), Added<BindingText>>,
+) {
+    if !input_map.is_changed() && added.is_empty() { return }
+    for (row, mut bg_color) in row_query.iter_mut() {
+        *bg_color = if input_map.listening == Some(row.action) {
+            Color::rgb(0.3, 0.4, 0.6).into()
+        } else {
+            Color::GRAY.into()
+        };
+    }
+    for (label, mut text) in text_query.iter_mut() {
+        text.sections[0].value = if input_map.listening == Some(label.action) {
+            format!("{}: press something, Esc cancels", label.action)
+        } else {
+            let bindings = input_map.bindings.get(&label.action).map(Vec::as_slice).unwrap_or_default();
+            let names: Vec<String> = bindings.iter().map(ToString::to_string).collect();
+            format!("{}: {}", label.action, names.join(", "))
+        };
+    }
+}
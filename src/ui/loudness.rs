@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use crate::audio::engine::Signal;
+use crate::audio::loudness::{Loudness, LoudnessReading};
+
+// Momentary bars span this range, the EBU R128 target sits at -23
+const BAR_FLOOR: f32 = -60.0; // in LUFS
+const TARGET: f32 = -23.0; // in LUFS
+// Headroom usually asked for, above this the bar turns red
+const TRUE_PEAK_CEILING: f32 = -1.0; // in dBTP
+
+#[derive(Component)]
+pub struct LoudnessList;
+
+#[derive(Component)]
+pub struct LoudnessRow {
+    pub signal: Signal,
+}
+
+#[derive(Component)]
+pub struct LoudnessText {
+    pub signal: Signal,
+}
+
+#[derive(Component)]
+pub struct LoudnessBar {
+    pub signal: Signal,
+}
+
+fn sources(loudness: &Loudness) -> Vec<(Signal, String, LoudnessReading)> {
+    std::iter::once((Signal::Master, "Master".to_string(), loudness.master))
+        .chain(loudness.emitters.iter().map(|(entity, name, reading)| (Signal::Emitter(*entity), name.clone(), *reading)))
+        .collect()
+}
+
+/// Respawns the rows whenever an emitter comes or goes.
+pub fn rebuild_loudness_list(
+    mut commands: Commands,
+    loudness: Res<Loudness>,
+    list_query: Query<Entity, With<LoudnessList>>,
+    rows: Query<&LoudnessRow>,
+) {
+    let Ok(list) = list_query.get_single() else { return };
+    let current: Vec<Signal> = rows.iter().map(|row| row.signal).collect();
+    let wanted = sources(&loudness);
+    if current == wanted.iter().map(|(signal, _, _)| *signal).collect::<Vec<_>>() { return }
+
+    commands.entity(list).despawn_descendants().with_children(|parent| {
+        for (signal, name, _) in wanted {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        padding: UiRect::all(Val::Px(6.0)),
+                        margin: UiRect::vertical(Val::Px(4.0)),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                },
+                LoudnessRow { signal },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section(
+                        name,
+                        TextStyle {
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                            ..default()
+                        }
+                    ),
+                    LoudnessText { signal },
+                ));
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(4.0),
+                        margin: UiRect::vertical(Val::Px(3.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::GREEN.into(),
+                            ..default()
+                        },
+                        LoudnessBar { signal },
+                    ));
+                });
+            });
+        }
+    });
+}
+
+pub fn update_loudness_rows(
+    loudness: Res<Loudness>,
+    mut text_query: Query<(&LoudnessText, &mut Text)>,
+    mut bar_query: Query<(&LoudnessBar, &mut Style, &mut BackgroundColor)>,
+) {
+    let sources = sources(&loudness);
+    let reading_for = |signal| sources.iter().find(|(source, _, _)| *source == signal);
+    for (label, mut text) in text_query.iter_mut() {
+        let Some((_, name, reading)) = reading_for(label.signal) else { continue };
+        let integrated = reading.integrated.map_or("--".to_string(), |lufs| format!("{lufs:.1}"));
+        text.sections[0].value = format!(
+            "{name}  I {integrated} LUFS\nM {:.1}  S {:.1}\nTP {:.1} dBTP  RMS {:.0} dB",
+            reading.momentary, reading.short_term, reading.true_peak, reading.rms,
+        );
+    }
+    for (bar, mut style, mut bg_color) in bar_query.iter_mut() {
+        let Some((_, _, reading)) = reading_for(bar.signal) else { continue };
+        let fraction = ((reading.momentary - BAR_FLOOR) / -BAR_FLOOR).clamp(0.0, 1.0);
+        style.width = Val::Percent(fraction * 100.0);
+        *bg_color = if reading.true_peak > TRUE_PEAK_CEILING {
+            Color::RED.into()
+        } else if reading.momentary > TARGET {
+            Color::YELLOW.into()
+        } else {
+            Color::GREEN.into()
+        };
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use crate::audio::engine::Listener;
+use crate::audio::microphone::{ListenerMeters, Monitor};
+use crate::audio::spectrum::{FLOOR_DB, SPECTRUM_BANDS};
+
+#[derive(Component)]
+pub struct MicrophoneList;
+
+/// Clicking a row solos its listener on the speakers.
+#[derive(Component)]
+pub struct MicrophoneRow {
+    pub listener: Listener,
+}
+
+#[derive(Component)]
+pub struct LevelText {
+    pub listener: Listener,
+}
+
+#[derive(Component)]
+pub struct LevelBar {
+    pub listener: Listener,
+}
+
+#[derive(Component)]
+pub struct SpectrumBar {
+    pub listener: Listener,
+    pub band: usize,
+}
+
+fn fraction(db: f32) -> f32 {
+    (1.0 - db / FLOOR_DB).clamp(0.0, 1.0)
+}
+
+/// Respawns the rows whenever a microphone is placed or removed.
+pub fn rebuild_microphone_list(
+    mut commands: Commands,
+    meters: Res<ListenerMeters>,
+    list_query: Query<Entity, With<MicrophoneList>>,
+    rows: Query<&MicrophoneRow>,
+) {
+    let Ok(list) = list_query.get_single() else { return };
+    let current: Vec<Listener> = rows.iter().map(|row| row.listener).collect();
+    let wanted: Vec<Listener> = meters.meters.iter().map(|meter| meter.listener).collect();
+    if current == wanted { return }
+
+    commands.entity(list).despawn_descendants().with_children(|parent| {
+        for meter in &meters.meters {
+            let listener = meter.listener;
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        padding: UiRect::all(Val::Px(6.0)),
+                        margin: UiRect::vertical(Val::Px(4.0)),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                },
+                MicrophoneRow { listener },
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_section(
+                        meter.name.clone(),
+                        TextStyle {
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                            ..default()
+                        }
+                    ),
+                    LevelText { listener },
+                ));
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(4.0),
+                        margin: UiRect::vertical(Val::Px(3.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::GREEN.into(),
+                            ..default()
+                        },
+                        LevelBar { listener },
+                    ));
+                });
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(24.0),
+                        align_items: AlignItems::FlexEnd,
+                        justify_content: JustifyContent::SpaceBetween,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for band in 0..SPECTRUM_BANDS {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(8.0),
+                                    height: Val::Percent(0.0),
+                                    ..default()
+                                },
+                                background_color: Color::rgb(0.4, 0.7, 1.0).into(),
+                                ..default()
+                            },
+                            SpectrumBar { listener, band },
+                        ));
+                    }
+                });
+            });
+        }
+    });
+}
+
+pub fn update_microphone_rows(
+    meters: Res<ListenerMeters>,
+    monitor: Res<Monitor>,
+    mut row_query: Query<(&MicrophoneRow, &mut BackgroundColor)>,
+    mut text_query: Query<(&LevelText, &mut Text)>,
+    mut bar_query: Query<(&LevelBar, &mut Style), Without<SpectrumBar>>,
+    mut spectrum_query: Query<(&SpectrumBar, &mut Style), Without<LevelBar>>,
+) {
+    let meter_for = |listener| meters.meters.iter().find(|meter| meter.listener == listener);
+    for (row, mut bg_color) in row_query.iter_mut() {
+        *bg_color = if row.listener == monitor.listener {
+            Color::rgb(0.3, 0.4, 0.6).into()
+        } else {
+            Color::GRAY.into()
+        };
+    }
+    for (level, mut text) in text_query.iter_mut() {
+        let Some(meter) = meter_for(level.listener) else { continue };
+        text.sections[0].value = format!("{}  {:.0} / {:.0} dB", meter.name, meter.rms, meter.peak);
+    }
+    for (bar, mut style) in bar_query.iter_mut() {
+        let Some(meter) = meter_for(bar.listener) else { continue };
+        style.width = Val::Percent(fraction(meter.rms) * 100.0);
+    }
+    for (bar, mut style) in spectrum_query.iter_mut() {
+        let Some(meter) = meter_for(bar.listener) else { continue };
+        style.height = Val::Percent(fraction(meter.spectrum[bar.band]) * 100.0);
+    }
+}
+
+pub fn solo_microphone(
+    interaction_query: Query<(&Interaction, &MicrophoneRow), Changed<Interaction>>,
+    mut monitor: ResMut<Monitor>,
+) {
+    for (interaction, row) in interaction_query.iter() {
+        if let Interaction::Pressed = interaction {
+            monitor.listener = row.listener;
+        }
+    }
+}
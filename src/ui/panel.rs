@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use crate::extras::components::*;
+use crate::extras::resources::*;
+use crate::input::map::{Action, ActionState};
+use crate::ui::bindings::BindingList;
+use crate::ui::loudness::LoudnessList;
+use crate::ui::microphones::MicrophoneList;
+use crate::ui::readouts::{ChordText, HeardChordText, RhythmText, TunerText, TuningText};
+
+pub fn setup_ui(
+    mut commands: Commands,
+) {
+    // UI draws on top of the 3D camera, a second camera would break the single camera queries
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Px(200.0),
+                height: Val::Percent(90.0),
+                position_type: PositionType::Absolute,
+                left: Val::Px(20.0),
+                top: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.2, 0.2, 0.2, 0.8).into(),
+            ..default()
+        },
+        UiPanel { collapsed: false },
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(30.0),
+                    height: Val::Px(30.0),
+                    margin: UiRect::all(Val::Px(5.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            Collapse,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                ">",
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ));
+        });
+        parent.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    padding: UiRect::horizontal(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            ContentContainer,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ButtonBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        padding: UiRect::all(Val::Px(10.0)),
+                        margin: UiRect::bottom(Val::Px(10.0)),
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::GRAY.into(),
+                    ..default()
+                },
+                ControlSection {
+                    section_type: SectionType::Speed,
+                    is_active: false,
+                },
+                SpeedDisplay,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "Speed",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ));
+                parent.spawn(TextBundle::from_section(
+                    "0.01",
+                    TextStyle {
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ));
+            });
+            parent.spawn((
+                TextBundle::from_section(
+                    "12-TET",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                TuningText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Tuner  -",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                TunerText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Emitters  -",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                ChordText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Heard  -",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                ),
+                HeardChordText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Tempo  -",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::WHITE,
+                        ..default()
+                    }
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(10.0)),
+                    ..default()
+                }),
+                RhythmText,
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Microphones",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                },
+                MicrophoneList,
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Loudness",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                },
+                LoudnessList,
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Controls",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                }
+            ));
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                },
+                BindingList,
+            ));
+        });
+    });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn toggle_speed_section(
+    mut interaction_query: Query<
+        (&Interaction, &mut ControlSection, &mut BackgroundColor),
+        (Changed<Interaction>, With<SpeedDisplay>)
+    >,
+    selection_state: Res<SelectionState>,
+) {
+    if selection_state.selected_entity.is_some() { return }
+    for (interaction, mut section, mut bg_color) in interaction_query.iter_mut() {
+        if let Interaction::Pressed = *interaction {
+            section.is_active = !section.is_active;
+            *bg_color = if section.is_active {
+                Color::rgb(0.3, 0.4, 0.6).into()
+            } else {
+                Color::GRAY.into()
+            };
+        }
+    }
+}
+
+pub fn update_collapse(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<Collapse>)>,
+    button_query: Query<&Children, With<Collapse>>,
+    mut text_query: Query<&mut Text>,
+    mut panel_query: Query<&mut UiPanel>,
+) {
+    let Ok(mut panel) = panel_query.get_single_mut() else { return };
+    if interaction_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        panel.collapsed = !panel.collapsed;
+    }
+    // Also catches the panel starting out collapsed from the saved settings
+    if !panel.is_changed() { return }
+    for children in button_query.iter() {
+        for &child in children {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.sections[0].value = if panel.collapsed { "^".to_string() } else { ">".to_string() };
+            }
+        }
+    }
+}
+
+pub fn content_visibility(
+    mut panel_query: Query<(&UiPanel, &mut Style)>,
+    mut content_query: Query<&mut Visibility, With<ContentContainer>>,
+) {
+    let (panel, mut style) = panel_query.single_mut();
+    style.height = if panel.collapsed {
+        Val::Px(40.0)
+    } else {
+        Val::Percent(90.0)
+    };
+    for mut visibility in content_query.iter_mut() {
+        *visibility = if panel.collapsed {
+            Visibility::Hidden
+        } else {
+            Visibility::Visible
+        };
+    }
+}
+
+pub fn update_speed_display(
+    sim_time: Res<SimulationTime>,
+    actions: Res<ActionState>,
+    mut section_query: Query<(&ControlSection, &Children, &mut BackgroundColor), With<SpeedDisplay>>,
+    mut text_query: Query<&mut Text>,
+    panel_query: Query<&UiPanel>,
+) {
+    let is_collapsed = panel_query.single().collapsed;
+    let (section, children, mut bg_color) = section_query.single_mut();
+    let is_active = !is_collapsed && section.is_active && (actions.pressed(Action::SpeedUp) || actions.pressed(Action::SpeedDown));
+
+    *bg_color = if section.is_active {
+        if is_active {
+            Color::rgb(0.4, 0.6, 0.8).into()
+        } else {
+            Color::rgb(0.3, 0.4, 0.6).into()
+        }
+    } else {
+        Color::GRAY.into()
+    };
+
+    if let Some(&last_child) = children.last() {
+        if let Ok(mut text) = text_query.get_mut(last_child) {
+            text.sections[0].value = format!("{:.3}", sim_time.speed_multiplier);
+        }
+    }
+}
+
+pub fn speed_input(
+    mut sim_time: ResMut<SimulationTime>,
+    actions: Res<ActionState>,
+    panel_query: Query<&UiPanel>,
+    section_query: Query<&ControlSection, With<SpeedDisplay>>,
+    selection_state: Res<SelectionState>,
+) {
+    if selection_state.selected_entity.is_some() { return }
+    let is_collapsed = panel_query.single().collapsed;
+    if is_collapsed { return }
+    let speed_section = section_query.single();
+    if !speed_section.is_active { return }
+
+    let speed_delta = 0.001;
+    if actions.pressed(Action::SpeedUp) {
+        sim_time.speed_multiplier = (sim_time.speed_multiplier + speed_delta).min(MAX_SPEED_MULTIPLIER);
+    }
+    if actions.pressed(Action::SpeedDown) {
+        sim_time.speed_multiplier = (sim_time.speed_multiplier - speed_delta).max(0.0);
+    }
+}
+
+/// Stops the simulation clock, and starts it again at the speed it had.
+pub fn toggle_pause(
+    mut sim_time: ResMut<SimulationTime>,
+    mut paused_speed: Local<Option<f32>>,
+) {
+    match paused_speed.take() {
+        Some(speed) if sim_time.speed_multiplier == 0.0 => sim_time.speed_multiplier = speed,
+        _ => {
+            *paused_speed = Some(sim_time.speed_multiplier);
+            sim_time.speed_multiplier = 0.0;
+        }
+    }
+}
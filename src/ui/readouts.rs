@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use crate::audio::harmony::HarmonyAnalysis;
+use crate::audio::pitch::DetectedPitch;
+use crate::audio::rhythm::{BeatEvent, OnsetEvent, RhythmTracker};
+use crate::music::chord::Harmony;
+use crate::music::tuning::Tuning;
+
+#[derive(Component)]
+pub struct TunerText;
+
+#[derive(Component)]
+pub struct TuningText;
+
+#[derive(Component)]
+pub struct RhythmText;
+
+/// The harmony the emitters are set to.
+#[derive(Component)]
+pub struct ChordText;
+
+/// The harmony found among the spectral peaks at the camera.
+#[derive(Component)]
+pub struct HeardChordText;
+
+fn describe(label: &str, harmony: Option<(Harmony, f32)>) -> String {
+    match harmony {
+        Some((harmony, detune)) if detune >= 1.0 => format!("{label}  {harmony} (±{detune:.0}c)"),
+        Some((harmony, _)) => format!("{label}  {harmony}"),
+        None => format!("{label}  -"),
+    }
+}
+
+pub fn update_tuner_text(
+    pitch: Res<DetectedPitch>,
+    mut text_query: Query<&mut Text, With<TunerText>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else { return };
+    text.sections[0].value = match (pitch.estimate, pitch.note) {
+        (Some(estimate), Some((note, cents))) => {
+            format!("Tuner  {note} {cents:+.0}c  ({:.1} Hz)", estimate.frequency)
+        }
+        _ => "Tuner  -".to_string(),
+    };
+    // Shaky estimates read dimmer
+    let clarity = pitch.estimate.map_or(1.0, |estimate| estimate.clarity);
+    text.sections[0].style.color = Color::WHITE.with_a(0.4 + 0.6 * clarity);
+}
+
+pub fn update_chord_text(
+    analysis: Res<HarmonyAnalysis>,
+    mut chord_query: Query<&mut Text, (With<ChordText>, Without<HeardChordText>)>,
+    mut heard_query: Query<&mut Text, (With<HeardChordText>, Without<ChordText>)>,
+) {
+    if let Ok(mut text) = chord_query.get_single_mut() {
+        text.sections[0].value = describe("Emitters", analysis.emitters);
+    }
+    if let Ok(mut text) = heard_query.get_single_mut() {
+        text.sections[0].value = describe("Heard", analysis.heard);
+    }
+}
+
+pub fn update_tuning_text(
+    tuning: Res<Tuning>,
+    mut text_query: Query<&mut Text, With<TuningText>>,
+) {
+    if !tuning.is_changed() { return }
+    let Ok(mut text) = text_query.get_single_mut() else { return };
+    text.sections[0].value = tuning.to_string();
+}
+
+/// Tempo, beat count and when the latest onset was heard.
+pub fn update_rhythm_text(
+    tracker: Res<RhythmTracker>,
+    mut onsets: EventReader<OnsetEvent>,
+    mut beats: EventReader<BeatEvent>,
+    mut latest: Local<(Option<f64>, Option<u64>)>,
+    mut text_query: Query<&mut Text, With<RhythmText>>,
+) {
+    if let Some(onset) = onsets.read().last() {
+        latest.0 = Some(onset.time);
+    }
+    if let Some(beat) = beats.read().last() {
+        latest.1 = Some(beat.index % 4 + 1);
+        debug!("Beat {} at {:.3} s, {:.0} BPM", beat.index, beat.time, beat.tempo);
+    }
+    let Ok(mut text) = text_query.get_single_mut() else { return };
+    let tempo = match (tracker.tempo, latest.1) {
+        (Some(tempo), Some(count)) => format!("{tempo:.0} BPM  {count}/4"),
+        (Some(tempo), None) => format!("{tempo:.0} BPM"),
+        _ => "-".to_string(),
+    };
+    let onset = latest.0.map_or(String::new(), |time| format!("  onset {time:.1} s"));
+    text.sections[0].value = format!("Tempo  {tempo}{onset}");
+}
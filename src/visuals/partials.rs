@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use crate::extras::components::{AudioEmitter, Harmonic};
+use crate::extras::resources::SimulationTime;
+
+// Satellites ring the emitter just outside its own pulse
+const ORBIT_RADIUS: f32 = 0.9;
+const ORBIT_SPEED: f32 = 0.5; // in radians per second
+// A partial as loud as the loudest one gets this radius
+const SATELLITE_RADIUS: f32 = 0.15;
+const PULSE: f32 = 0.3;
+
+/// A small sphere standing for one partial above an emitter's fundamental.
+#[derive(Component)]
+pub struct Satellite {
+    pub emitter: Entity,
+    pub index: usize,
+}
+
+/// Every partial but the fundamental, which is the emitter itself.
+fn overtones(emitter: &AudioEmitter) -> impl Iterator<Item = &Harmonic> {
+    emitter.partials.iter().filter(|partial| partial.ratio != 1.0)
+}
+
+/// Respawns an emitter's satellites whenever its number of partials changes.
+pub fn sync_satellites(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    emitter_query: Query<(Entity, &AudioEmitter, Option<&Handle<StandardMaterial>>)>,
+    satellites: Query<(Entity, &Satellite)>,
+    mut mesh: Local<Option<Handle<Mesh>>>,
+    mut spawned: Local<Vec<(Entity, usize)>>,
+) {
+    for (entity, satellite) in satellites.iter() {
+        if emitter_query.get(satellite.emitter).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+    spawned.retain(|(emitter, _)| emitter_query.get(*emitter).is_ok());
+
+    let mesh = mesh.get_or_insert_with(|| meshes.add(Sphere::new(1.0).mesh())).clone();
+    for (emitter_entity, emitter, material) in emitter_query.iter() {
+        let overtones = overtones(emitter).count();
+        let known = spawned.iter().find(|(entity, _)| *entity == emitter_entity).map(|(_, count)| *count);
+        if known == Some(overtones) { continue }
+        for (entity, satellite) in satellites.iter() {
+            if satellite.emitter == emitter_entity {
+                commands.entity(entity).despawn();
+            }
+        }
+        spawned.retain(|(entity, _)| *entity != emitter_entity);
+        spawned.push((emitter_entity, overtones));
+
+        let color = material.and_then(|handle| materials.get(handle)).map_or(Color::WHITE, |m| m.base_color);
+        for index in 0..overtones {
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: materials.add(StandardMaterial {
+                        base_color: color,
+                        emissive: color * 0.6,
+                        ..default()
+                    }),
+                    transform: Transform::from_scale(Vec3::ZERO),
+                    ..default()
+                },
+                Satellite { emitter: emitter_entity, index },
+            ));
+        }
+    }
+}
+
+/// Orbits each satellite around its emitter, sized by its partial's amplitude and pulsing
+/// at its frequency like the emitter does at the fundamental.
+pub fn animate_satellites(
+    sim_time: Res<SimulationTime>,
+    emitter_query: Query<(&AudioEmitter, &GlobalTransform)>,
+    mut satellites: Query<(&Satellite, &mut Transform)>,
+) {
+    for (satellite, mut transform) in satellites.iter_mut() {
+        let Ok((emitter, emitter_transform)) = emitter_query.get(satellite.emitter) else { continue };
+        let count = overtones(emitter).count();
+        let Some(partial) = overtones(emitter).nth(satellite.index) else { continue };
+        let loudest = emitter.partials.iter().map(|partial| partial.amplitude).fold(f32::EPSILON, f32::max);
+
+        let angle = satellite.index as f32 / count as f32 * TAU + sim_time.elapsed * ORBIT_SPEED;
+        // Higher partials sit a little higher, so the ring reads as a stack from the side
+        let offset = Vec3::new(angle.cos() * ORBIT_RADIUS, 0.1 * satellite.index as f32, angle.sin() * ORBIT_RADIUS);
+        let wave = (emitter.frequency * partial.ratio * sim_time.elapsed * TAU + emitter.phase + partial.phase).sin();
+        let radius = SATELLITE_RADIUS * (partial.amplitude / loudest).sqrt() * (1.0 + wave * PULSE);
+
+        transform.translation = emitter_transform.translation() + offset;
+        transform.scale = Vec3::splat(radius);
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+use crate::audio::rhythm::{BeatEvent, OnsetEvent};
+
+// How quickly a flash or bump dies away, per second
+const FLASH_DECAY: f32 = 8.0;
+const BUMP_DECAY: f32 = 10.0;
+// Emissive share of the base color at rest and at the height of a flash
+const REST_GLOW: f32 = 0.2;
+const FLASH_GLOW: f32 = 1.5;
+// Field of view narrows by this share on a beat, a quick push in
+const BUMP: f32 = 0.04;
+const FIELD_OF_VIEW: f32 = PI / 4.0;
+
+/// Lights an emitter's material up on every onset.
+#[derive(Component, Default)]
+pub struct Flash {
+    pub level: f32,
+}
+
+pub fn flash_on_onsets(
+    time: Res<Time>,
+    mut onsets: EventReader<OnsetEvent>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut flash_query: Query<(&mut Flash, &Handle<StandardMaterial>)>,
+) {
+    let strongest = onsets.read().map(|onset| onset.strength).fold(0.0, f32::max);
+    for (mut flash, handle) in flash_query.iter_mut() {
+        let previous = flash.level;
+        if strongest > 0.0 {
+            flash.level = 1.0;
+        } else if flash.level > 1e-3 {
+            flash.level *= (-FLASH_DECAY * time.delta_seconds()).exp();
+        } else {
+            flash.level = 0.0;
+        }
+        if previous == flash.level { continue }
+        if let Some(material) = materials.get_mut(handle) {
+            material.emissive = material.base_color * (REST_GLOW + (FLASH_GLOW - REST_GLOW) * flash.level);
+        }
+    }
+}
+
+pub fn bump_camera_on_beats(
+    time: Res<Time>,
+    mut beats: EventReader<BeatEvent>,
+    mut level: Local<f32>,
+    mut camera_query: Query<&mut Projection, With<Camera3d>>,
+) {
+    if beats.read().count() > 0 {
+        *level = 1.0;
+    } else if *level > 0.0 {
+        *level *= (-BUMP_DECAY * time.delta_seconds()).exp();
+        if *level < 1e-3 {
+            *level = 0.0;
+        }
+    } else {
+        return;
+    }
+    for mut projection in camera_query.iter_mut() {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = FIELD_OF_VIEW * (1.0 - BUMP * *level);
+        }
+    }
+}
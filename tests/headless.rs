@@ -0,0 +1,55 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use fundamentals::acoustics::material::MaterialLibrary;
+use fundamentals::audio::offline::OfflineAudio;
+use fundamentals::components::AudioEmitter;
+use fundamentals::music::timbre::Instrument;
+use fundamentals::resources::SimulationTime;
+use fundamentals::scene::layout::SceneLayout;
+use fundamentals::simulation::TICK_SAMPLES;
+use fundamentals::{AudioSimPlugin, HeadlessPlugin};
+
+const TICKS: u32 = 30;
+
+// One emitter heard from the camera, in the default room
+fn scene(mut commands: Commands) {
+    commands.spawn((Camera3d::default(), TransformBundle::from_transform(Transform::from_xyz(0.0, 1.2, 6.0))));
+    commands.spawn((
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 0.5, 0.0)),
+        AudioEmitter { frequency: 440.0, amplitude: 0.3, phase: 0.0, partials: Instrument::Sine.partials() },
+    ));
+}
+
+// Steps the app by hand until it exits, then returns what it rendered
+fn run() -> (u64, u64, Vec<f32>) {
+    let mut app = App::new();
+    // Built-in defaults only, whatever files are in the working directory
+    app.insert_resource(SceneLayout::default())
+        .insert_resource(MaterialLibrary::default())
+        .add_plugins((HeadlessPlugin { ticks: TICKS, render: None }, AudioSimPlugin))
+        .insert_resource(OfflineAudio::recording())
+        .add_systems(Startup, scene);
+    app.finish();
+    app.cleanup();
+    for _ in 0..TICKS * 2 {
+        app.update();
+        if !app.world.resource::<Events<AppExit>>().is_empty() { break }
+    }
+    assert!(!app.world.resource::<Events<AppExit>>().is_empty(), "the run never exited");
+
+    let tick = app.world.resource::<SimulationTime>().tick;
+    let mut offline = app.world.resource_mut::<OfflineAudio>();
+    (tick, offline.rendered(), offline.take_recording().unwrap())
+}
+
+#[test]
+fn headless_runs_are_exact_and_repeatable() {
+    let (tick, rendered, first) = run();
+    assert_eq!(tick, TICKS as u64);
+    assert_eq!(rendered, TICKS as u64 * TICK_SAMPLES as u64);
+    assert_eq!(first.len() as u64, rendered);
+    assert!(first.iter().any(|sample| *sample != 0.0), "the emitter was never heard");
+
+    let (_, _, second) = run();
+    assert!(first == second, "two runs rendered different audio");
+}